@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use log::info;
+
+use crate::chain_editor::config_file::{insert_chain, read_document, write_document};
+use crate::config::{color_default, AppConfig, Chain, EncryptionType, RpcEndpoint, RpcSettings};
+use crate::config_validator::validate_config;
+use crate::ethereum::is_ethereum_metadata;
+use crate::fetch::{build_specs, fetch_meta, fetch_properties, metadata_format, RpcClient};
+use crate::opts::AddChainOpts;
+use crate::source::{save_source_info, Source};
+use crate::updater::generate::{generate_metadata_qr, generate_spec_qr};
+
+pub(crate) fn add_chain(config: AppConfig, config_path: &Path, opts: AddChainOpts) -> Result<()> {
+    info!("🔍 Fetching chain info from {}...", opts.rpc);
+    let endpoint = RpcEndpoint::from(opts.rpc.as_str());
+    let client = RpcClient::connect(&endpoint, &config.rpc)?;
+    let meta = fetch_meta(&client, None, None)?;
+    let encryption = match is_ethereum_metadata(&meta.meta_values.meta) {
+        Ok(Some(true)) => Some(EncryptionType::Ethereum),
+        _ => None,
+    };
+    let chain = Chain {
+        name: meta.meta_values.name.to_lowercase(),
+        title: opts.title,
+        color: opts.color.unwrap_or_else(color_default),
+        rpc_endpoints: vec![endpoint],
+        token_unit: None,
+        token_decimals: None,
+        genesis_hash: Some(format!("0x{}", hex::encode(meta.genesis_hash))),
+        metadata_version: None,
+        github_release: None,
+        relay_chain: opts.relay,
+        encryption,
+        tags: vec![],
+        rpc: RpcSettings::default(),
+        update_sources: vec![],
+    };
+    if config
+        .chains
+        .iter()
+        .any(|c| c.portal_id() == chain.portal_id())
+    {
+        bail!("{} is already configured", chain.portal_id());
+    }
+    let mut new_config = config.clone();
+    new_config.chains.push(chain.clone());
+    validate_config(&new_config)?;
+
+    let properties = fetch_properties(&client)?;
+    let specs = build_specs(&chain, &meta.meta_values, meta.genesis_hash, &properties)
+        .context("You may need to set `token_unit` and `token_decimals` manually")?;
+
+    let mut doc = read_document(config_path)?;
+    insert_chain(&mut doc, &chain);
+    write_document(config_path, &doc)?;
+    info!(
+        "✅ {} added to {}",
+        chain.portal_id(),
+        config_path.display()
+    );
+
+    if opts.generate {
+        generate_spec_qr(&specs, &config.qr_dir, &chain.portal_id())?;
+        let path = generate_metadata_qr(
+            &meta.meta_values,
+            &meta.genesis_hash,
+            &config.qr_dir,
+            &chain.portal_id(),
+        )?;
+        save_source_info(
+            &path,
+            &Source::Rpc {
+                block: meta.block_hash,
+                number: meta.block_number,
+                metadata_format: metadata_format(&meta.meta_values.meta),
+            },
+        )?;
+    }
+    Ok(())
+}
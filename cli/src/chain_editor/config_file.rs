@@ -0,0 +1,186 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use tempfile::NamedTempFile;
+use toml_edit::{value, ArrayOfTables, Document, Item, Table};
+
+use crate::config::{color_default, Chain};
+
+const CHAINS_KEY: &str = "chains";
+
+// Read config.toml keeping its formatting and comments
+pub(crate) fn read_document(path: &Path) -> Result<Document> {
+    let content = fs::read_to_string(path).context(format!("{}", path.display()))?;
+    Ok(content.parse::<Document>()?)
+}
+
+// Write to a temporary file next to the config and move it over the config,
+// so that it is never left half-written
+pub(crate) fn write_document(path: &Path, doc: &Document) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut file = NamedTempFile::new_in(dir).context(format!("{}", dir.display()))?;
+    file.write_all(doc.to_string().as_bytes())?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(file.path(), metadata.permissions())?;
+    }
+    file.persist(path).context(format!("{}", path.display()))?;
+    Ok(())
+}
+
+// Insert the chain right after the last chain of the same relay or append it to the end
+pub(crate) fn insert_chain(doc: &mut Document, chain: &Chain) {
+    if doc.get(CHAINS_KEY).is_none() {
+        doc[CHAINS_KEY] = Item::ArrayOfTables(ArrayOfTables::new());
+    }
+    let chains = doc[CHAINS_KEY].as_array_of_tables_mut().unwrap();
+
+    let mut tables: Vec<Table> = chains.iter().cloned().collect();
+    let position = chain
+        .relay_chain
+        .as_ref()
+        .and_then(|relay| {
+            tables.iter().rposition(|t| {
+                table_str(t, "name") == Some(relay) || table_str(t, "relay_chain") == Some(relay)
+            })
+        })
+        .map(|i| i + 1)
+        .unwrap_or(tables.len());
+    tables.insert(position, chain_to_table(chain));
+
+    chains.clear();
+    for table in tables {
+        chains.push(table);
+    }
+}
+
+pub(crate) fn remove_chain(doc: &mut Document, chain: &Chain) -> Result<()> {
+    let (chains, index) = find_chain(doc, chain)?;
+    chains.remove(index);
+    Ok(())
+}
+
+pub(crate) fn rename_chain(doc: &mut Document, chain: &Chain, renamed: &Chain) -> Result<()> {
+    let (chains, index) = find_chain(doc, chain)?;
+    let table = chains.get_mut(index).unwrap();
+    if renamed.name != chain.name {
+        table["name"] = value(&renamed.name);
+    }
+    if renamed.relay_chain != chain.relay_chain {
+        match &renamed.relay_chain {
+            Some(relay) => table["relay_chain"] = value(relay),
+            None => {
+                table.remove("relay_chain");
+            }
+        }
+    }
+    Ok(())
+}
+
+// Locate the chain table. Chain names are case-insensitive in config
+fn find_chain<'a>(doc: &'a mut Document, chain: &Chain) -> Result<(&'a mut ArrayOfTables, usize)> {
+    let chains = match doc
+        .get_mut(CHAINS_KEY)
+        .and_then(|i| i.as_array_of_tables_mut())
+    {
+        Some(chains) => chains,
+        None => bail!("no chains found in config"),
+    };
+    let index = chains.iter().position(|t| {
+        table_str(t, "name").map(|n| n.to_lowercase()).as_ref() == Some(&chain.name)
+            && table_str(t, "relay_chain") == chain.relay_chain.as_deref()
+    });
+    match index {
+        Some(index) => Ok((chains, index)),
+        None => bail!(
+            "{} is not found in config. Is it defined in an included file?",
+            chain.portal_id()
+        ),
+    }
+}
+
+pub(crate) fn table_str<'a>(table: &'a Table, key: &str) -> Option<&'a str> {
+    table.get(key).and_then(|v| v.as_str())
+}
+
+fn chain_to_table(chain: &Chain) -> Table {
+    let mut table = Table::new();
+    table.decor_mut().set_prefix("\n");
+    table["name"] = value(&chain.name);
+    if let Some(title) = &chain.title {
+        table["title"] = value(title);
+    }
+    table["rpc_endpoint"] = value(&chain.rpc_endpoints[0].url);
+    if chain.color != color_default() {
+        table["color"] = value(&chain.color);
+    }
+    if let Some(relay) = &chain.relay_chain {
+        table["relay_chain"] = value(relay);
+    }
+    if let Some(genesis_hash) = &chain.genesis_hash {
+        table["genesis_hash"] = value(genesis_hash);
+    }
+    if let Some(encryption) = chain.encryption {
+        table["encryption"] = value(encryption.to_string());
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"data_file = "public/data.json"
+
+# Polkadot and its parachains
+[[chains]]
+name = "polkadot"
+rpc_endpoint = "wss://rpc.polkadot.io"
+
+[[chains]]
+name = "statemint"
+relay_chain = "polkadot"
+
+[[chains]]
+name = "kusama"
+rpc_endpoint = "wss://kusama-rpc.polkadot.io"
+"#;
+
+    #[test]
+    fn insert_parachain_after_its_relay() {
+        let mut doc = CONFIG.parse::<Document>().unwrap();
+        let chain = Chain {
+            name: "collectives".to_string(),
+            rpc_endpoints: vec!["wss://collectives.io".into()],
+            relay_chain: Some("polkadot".to_string()),
+            ..Chain::default()
+        };
+        insert_chain(&mut doc, &chain);
+
+        let expected = CONFIG.replace(
+            "\n[[chains]]\nname = \"kusama\"",
+            "\n[[chains]]\nname = \"collectives\"\nrpc_endpoint = \"wss://collectives.io\"\nrelay_chain = \"polkadot\"\n\n[[chains]]\nname = \"kusama\"",
+        );
+        assert_eq!(doc.to_string(), expected);
+    }
+
+    #[test]
+    fn append_relay_chain() {
+        let mut doc = CONFIG.parse::<Document>().unwrap();
+        let chain = Chain {
+            name: "westend".to_string(),
+            rpc_endpoints: vec!["wss://westend.io".into()],
+            ..Chain::default()
+        };
+        insert_chain(&mut doc, &chain);
+
+        let expected = format!(
+            "{CONFIG}\n[[chains]]\nname = \"westend\"\nrpc_endpoint = \"wss://westend.io\"\n"
+        );
+        assert_eq!(doc.to_string(), expected);
+    }
+}
@@ -0,0 +1,9 @@
+mod add;
+mod config_file;
+mod plan;
+mod remove;
+mod rename;
+
+pub(crate) use add::add_chain;
+pub(crate) use remove::remove_chain;
+pub(crate) use rename::rename_chain;
@@ -0,0 +1,306 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context, Result};
+use log::{error, info, warn};
+use toml_edit::Document;
+
+use crate::chain_editor::config_file::write_document;
+use crate::common::path::{latest_metadata_path, QrPath};
+use crate::qrs::qrs_in_dir;
+
+/// Change of a single file in the QR directory
+#[derive(Debug, PartialEq)]
+pub(crate) enum FileOp {
+    Remove(PathBuf),
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+impl FileOp {
+    fn source(&self) -> &Path {
+        match self {
+            FileOp::Remove(path) => path,
+            FileOp::Rename { from, .. } => from,
+        }
+    }
+}
+
+impl fmt::Display for FileOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileOp::Remove(path) => write!(f, "🗑  remove {}", path.display()),
+            FileOp::Rename { from, to } => {
+                write!(f, "🚚 rename {} -> {}", from.display(), to.display())
+            }
+        }
+    }
+}
+
+/// Everything a chain migration is going to change
+#[derive(Debug, Default)]
+pub(crate) struct Plan {
+    pub(crate) config: Vec<String>,
+    pub(crate) files: Vec<FileOp>,
+}
+
+impl Plan {
+    pub(crate) fn print(&self) {
+        info!("📝 config.toml:");
+        for change in &self.config {
+            info!("   {}", change);
+        }
+        info!("📁 QR files:");
+        if self.files.is_empty() {
+            info!("   nothing to change");
+        }
+        for op in &self.files {
+            info!("   {}", op);
+        }
+    }
+
+    // Make sure no file is going to be overwritten before touching anything.
+    // Files moved away by the same plan can be replaced
+    pub(crate) fn check(&self) -> Result<()> {
+        let mut targets = vec![];
+        for op in &self.files {
+            if let FileOp::Rename { to, .. } = op {
+                let is_moved = self.files.iter().any(|op| op.source() == to);
+                ensure!(!to.exists() || is_moved, "{} already exists", to.display());
+                ensure!(!targets.contains(&to), "{} is renamed twice", to.display());
+                targets.push(to);
+            }
+        }
+        Ok(())
+    }
+
+    // Apply file changes and write `doc` to `config_path` last.
+    // Files are first moved to temporary names and only then to their final names,
+    // any error rolls back everything done so far
+    pub(crate) fn apply(&self, config_path: &Path, doc: &Document) -> Result<()> {
+        // Original and temporary path of every staged file
+        let mut staged = vec![];
+        // Temporary and final path of every renamed file
+        let mut moved = vec![];
+        let result = self
+            .stage_and_move(&mut staged, &mut moved)
+            .and_then(|()| write_document(config_path, doc));
+        if let Err(e) = result {
+            rollback(&staged, &moved);
+            return Err(e);
+        }
+        for (op, (_, temp)) in self.files.iter().zip(&staged) {
+            if let FileOp::Remove(_) = op {
+                if let Err(e) = fs::remove_file(temp) {
+                    warn!("Failed to remove {}: {}", temp.display(), e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn stage_and_move(
+        &self,
+        staged: &mut Vec<(PathBuf, PathBuf)>,
+        moved: &mut Vec<(PathBuf, PathBuf)>,
+    ) -> Result<()> {
+        for op in &self.files {
+            let from = op.source();
+            let temp = staging_path(from);
+            fs::rename(from, &temp).context(format!("{}", from.display()))?;
+            staged.push((from.to_path_buf(), temp));
+        }
+        for (op, (_, temp)) in self.files.iter().zip(staged.iter()) {
+            if let FileOp::Rename { to, .. } = op {
+                fs::rename(temp, to).context(format!("{}", to.display()))?;
+                moved.push((temp.clone(), to.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn staging_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.migrating"))
+}
+
+// Move renamed files back to their temporary names and then every file to its original name
+fn rollback(staged: &[(PathBuf, PathBuf)], moved: &[(PathBuf, PathBuf)]) {
+    let undo = moved
+        .iter()
+        .rev()
+        .map(|(temp, to)| (to, temp))
+        .chain(staged.iter().rev().map(|(from, temp)| (temp, from)));
+    for (from, to) in undo {
+        if let Err(e) = fs::rename(from, to) {
+            error!(
+                "Failed to restore {} from {}: {}",
+                to.display(),
+                from.display(),
+                e
+            );
+        }
+    }
+}
+
+// Remove all signed and unsigned QRs of the chain
+pub(crate) fn remove_files(qr_dir: &Path, portal_id: &str) -> Result<Vec<FileOp>> {
+    let mut ops: Vec<FileOp> = chain_qrs(qr_dir, portal_id)?
+        .into_iter()
+        .map(|qr| FileOp::Remove(qr.to_path_buf()))
+        .collect();
+    ops.extend(remove_latest_pointer(qr_dir, portal_id));
+    Ok(ops)
+}
+
+// Move all signed and unsigned QRs of the chain to the new portal id.
+// The pointer to the latest metadata is re-created by the collector.
+pub(crate) fn rename_files(qr_dir: &Path, from: &str, to: &str) -> Result<Vec<FileOp>> {
+    let mut ops: Vec<FileOp> = chain_qrs(qr_dir, from)?
+        .into_iter()
+        .map(|qr| {
+            let mut renamed = qr.clone();
+            renamed.file_name.chain = to.to_string();
+            FileOp::Rename {
+                from: qr.to_path_buf(),
+                to: renamed.to_path_buf(),
+            }
+        })
+        .collect();
+    ops.extend(remove_latest_pointer(qr_dir, from));
+    Ok(ops)
+}
+
+fn chain_qrs(qr_dir: &Path, portal_id: &str) -> Result<Vec<QrPath>> {
+    let mut qrs: Vec<QrPath> = qrs_in_dir(qr_dir)?
+        .into_iter()
+        .filter(|qr| qr.file_name.chain == portal_id)
+        .collect();
+    qrs.sort_by_key(|qr| qr.file_name.to_string());
+    Ok(qrs)
+}
+
+fn remove_latest_pointer(qr_dir: &Path, portal_id: &str) -> Option<FileOp> {
+    let pointer = latest_metadata_path(qr_dir, portal_id);
+    pointer.is_symlink().then_some(FileOp::Remove(pointer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_signed_and_unsigned_qrs() {
+        let qr_dir = Path::new("./src/for_tests/unsigned");
+        let ops = rename_files(qr_dir, "polkadot", "polkadot-new").unwrap();
+        assert_eq!(
+            ops,
+            vec![
+                FileOp::Rename {
+                    from: qr_dir.join("polkadot_metadata_9001"),
+                    to: qr_dir.join("polkadot-new_metadata_9001"),
+                },
+                FileOp::Rename {
+                    from: qr_dir.join("unsigned_polkadot_metadata_9002"),
+                    to: qr_dir.join("unsigned_polkadot-new_metadata_9002"),
+                },
+                FileOp::Rename {
+                    from: qr_dir.join("unsigned_polkadot_specs.png"),
+                    to: qr_dir.join("unsigned_polkadot-new_specs.png"),
+                },
+            ]
+        );
+    }
+
+    fn plan(dir: &Path) -> Plan {
+        for file in ["a", "b", "c"] {
+            fs::write(dir.join(file), file).unwrap();
+        }
+        // Swap `a` and `b`, remove `c`
+        Plan {
+            config: vec![],
+            files: vec![
+                FileOp::Rename {
+                    from: dir.join("a"),
+                    to: dir.join("b"),
+                },
+                FileOp::Rename {
+                    from: dir.join("b"),
+                    to: dir.join("a"),
+                },
+                FileOp::Remove(dir.join("c")),
+            ],
+        }
+    }
+
+    fn read(dir: &Path) -> Vec<(String, String)> {
+        let mut files: Vec<(String, String)> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap().to_string_lossy().to_string();
+                (name, fs::read_to_string(path).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn apply_plan() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = plan(dir.path());
+        plan.check().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let doc: Document = "qr_dir = \"qr\"\n".parse().unwrap();
+        plan.apply(&config_path, &doc).unwrap();
+        assert_eq!(
+            read(dir.path()),
+            vec![
+                ("a".to_string(), "b".to_string()),
+                ("b".to_string(), "a".to_string()),
+                ("config.toml".to_string(), doc.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn roll_back_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan = plan(dir.path());
+        let before = read(dir.path());
+        // The config can't be written to a missing directory
+        let config_path = dir.path().join("missing").join("config.toml");
+        assert!(plan.apply(&config_path, &Document::new()).is_err());
+        assert_eq!(read(dir.path()), before);
+    }
+
+    #[test]
+    fn check_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut plan = plan(dir.path());
+        assert!(plan.check().is_ok());
+
+        fs::write(dir.path().join("d"), "d").unwrap();
+        plan.files.push(FileOp::Rename {
+            from: dir.path().join("x"),
+            to: dir.path().join("d"),
+        });
+        assert!(plan.check().is_err());
+
+        plan.files.pop();
+        plan.files.push(FileOp::Rename {
+            from: dir.path().join("x"),
+            to: dir.path().join("a"),
+        });
+        assert!(plan.check().is_err());
+    }
+
+    #[test]
+    fn remove_only_chain_qrs() {
+        let qr_dir = Path::new("./src/for_tests/different_chains");
+        let ops = remove_files(qr_dir, "kusama").unwrap();
+        assert_eq!(ops, vec![FileOp::Remove(qr_dir.join("kusama_metadata_9"))]);
+    }
+}
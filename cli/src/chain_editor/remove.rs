@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use log::info;
+
+use crate::chain_editor::config_file::{read_document, remove_chain as remove_from_document};
+use crate::chain_editor::plan::{remove_files, Plan};
+use crate::collector::collect;
+use crate::config::AppConfig;
+use crate::config_validator::validate_config;
+use crate::opts::RemoveChainOpts;
+
+pub(crate) fn remove_chain(
+    config: AppConfig,
+    config_path: &Path,
+    opts: RemoveChainOpts,
+) -> Result<()> {
+    let chain = config
+        .chains
+        .iter()
+        .find(|c| c.portal_id() == opts.portal_id)
+        .context(format!("{} is not configured", opts.portal_id))?;
+
+    let mut new_config = config.clone();
+    new_config
+        .chains
+        .retain(|c| c.portal_id() != chain.portal_id());
+    validate_config(&new_config)?;
+
+    let mut doc = read_document(config_path)?;
+    remove_from_document(&mut doc, chain)?;
+    let plan = Plan {
+        config: vec![format!("remove {}", chain.portal_id())],
+        files: remove_files(&config.qr_dir, &chain.portal_id())?,
+    };
+    plan.print();
+    plan.check()?;
+    if opts.dry_run {
+        return Ok(());
+    }
+
+    plan.apply(config_path, &doc)?;
+    info!("✅ {} removed", chain.portal_id());
+    collect(AppConfig::load(config_path)?, None)
+}
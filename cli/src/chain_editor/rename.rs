@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use anyhow::{ensure, Context, Result};
+use log::info;
+
+use crate::chain_editor::config_file::{read_document, rename_chain as rename_in_document};
+use crate::chain_editor::plan::{rename_files, Plan};
+use crate::collector::collect;
+use crate::config::{AppConfig, Chain};
+use crate::config_validator::validate_config;
+use crate::opts::RenameChainOpts;
+
+pub(crate) fn rename_chain(
+    config: AppConfig,
+    config_path: &Path,
+    opts: RenameChainOpts,
+) -> Result<()> {
+    let chain = config
+        .chains
+        .iter()
+        .find(|c| c.portal_id() == opts.portal_id)
+        .context(format!("{} is not configured", opts.portal_id))?;
+
+    let mut renamed = chain.clone();
+    if let Some(name) = opts.name {
+        renamed.name = name.to_lowercase();
+    }
+    if let Some(relay) = opts.relay {
+        renamed.relay_chain = Some(relay);
+    }
+    ensure!(
+        renamed.portal_id() != chain.portal_id(),
+        "{} already has this name",
+        chain.portal_id()
+    );
+
+    let renames = chains_to_rename(&config, chain, &renamed);
+    let mut new_config = config.clone();
+    for c in new_config.chains.iter_mut() {
+        if let Some((_, new)) = renames
+            .iter()
+            .find(|(old, _)| old.portal_id() == c.portal_id())
+        {
+            *c = new.clone();
+        }
+    }
+    validate_config(&new_config)?;
+
+    let mut doc = read_document(config_path)?;
+    let mut plan = Plan::default();
+    for (old, new) in &renames {
+        rename_in_document(&mut doc, old, new)?;
+        plan.config
+            .push(format!("rename {} -> {}", old.portal_id(), new.portal_id()));
+        plan.files.extend(rename_files(
+            &config.qr_dir,
+            &old.portal_id(),
+            &new.portal_id(),
+        )?);
+    }
+    plan.print();
+    plan.check()?;
+    if opts.dry_run {
+        return Ok(());
+    }
+
+    plan.apply(config_path, &doc)?;
+    info!(
+        "✅ {} renamed to {}",
+        chain.portal_id(),
+        renamed.portal_id()
+    );
+    collect(AppConfig::load(config_path)?, None)
+}
+
+// Parachains follow the renamed relay chain
+fn chains_to_rename(config: &AppConfig, chain: &Chain, renamed: &Chain) -> Vec<(Chain, Chain)> {
+    let mut renames = vec![(chain.clone(), renamed.clone())];
+    if chain.relay_chain.is_some() || chain.name == renamed.name {
+        return renames;
+    }
+    for parachain in &config.chains {
+        if parachain.relay_chain.as_ref() == Some(&chain.name) {
+            let mut renamed_parachain = parachain.clone();
+            renamed_parachain.relay_chain = Some(renamed.name.clone());
+            renames.push((parachain.clone(), renamed_parachain));
+        }
+    }
+    renames
+}
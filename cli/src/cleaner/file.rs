@@ -4,14 +4,24 @@ use std::path::PathBuf;
 
 use anyhow::Context;
 
+use crate::common::path::QrPath;
 use crate::file::files_to_keep;
 use crate::AppConfig;
 
 pub(crate) fn files_to_remove(config: &AppConfig) -> anyhow::Result<Vec<PathBuf>> {
-    let all_files: HashSet<PathBuf> = fs::read_dir(&config.qr_dir)
+    let mut all_files: HashSet<PathBuf> = fs::read_dir(&config.qr_dir)
         .context(format!("{}", config.qr_dir.display()))?
         .map(|f| f.unwrap().path())
         .collect();
+    // Only touch files of the selected chains
+    if !config.filter.is_empty() {
+        let selected: HashSet<String> = config.selected_chains().map(|c| c.portal_id()).collect();
+        all_files.retain(|path| {
+            QrPath::try_from(path)
+                .map(|qr| selected.contains(&qr.file_name.chain))
+                .unwrap_or(false)
+        });
+    }
 
     let keep_files = files_to_keep(config)?
         .iter()
@@ -88,4 +98,15 @@ mod tests {
         let to_remove = files_to_remove(&config).unwrap();
         assert_eq!(to_remove.len(), 0);
     }
+
+    #[test]
+    fn remove_only_selected_chains() {
+        let mut config = AppConfig::default();
+        config.filter.chains = vec!["polkadot".to_string()];
+        config.qr_dir = PathBuf::from("./src/cleaner/for_tests/test4/qrs");
+        config.data_file = config.qr_dir.join("../data.json");
+
+        let to_remove = files_to_remove(&config).unwrap();
+        assert_eq!(to_remove.len(), 0);
+    }
 }
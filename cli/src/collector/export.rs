@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::os::unix::fs::symlink;
 use std::path::PathBuf;
@@ -6,22 +7,56 @@ use anyhow::{Context, Result};
 use indexmap::IndexMap;
 use log::{info, warn};
 
-use crate::common::path::{ContentType, QrPath};
-use crate::common::types::MetaVersion;
-use crate::export::{ExportChainSpec, ExportData, MetadataQr, QrCode, ReactAssetPath};
-use crate::fetch::{fetch_deployed_data, Fetcher};
+use crate::common::path::{latest_metadata_path, ContentType, QrPath};
+use crate::common::types::{ChainPortalId, MetaVersion};
+use crate::config::Chain;
+use crate::ethereum::check_account_type;
+use crate::export::{
+    read_export_file, ExportChainSpec, ExportData, MetadataQr, QrCode, ReactAssetPath,
+};
+use crate::fetch::{fetch_deployed_data, fetch_parallel, metadata_format, Fetcher};
 use crate::qrs::{collect_metadata_qrs, metadata_files, spec_files};
 use crate::AppConfig;
 
-pub(crate) fn export_specs(config: &AppConfig, fetcher: impl Fetcher) -> Result<ExportData> {
+pub(crate) fn export_specs(config: &AppConfig, fetcher: impl Fetcher + Sync) -> Result<ExportData> {
     let all_specs = spec_files(&config.qr_dir)?;
     let all_metadata = metadata_files(&config.qr_dir)?;
     let online = fetch_deployed_data(config).ok();
+    // Chains that are not selected keep their current entries
+    let current = match config.filter.is_empty() {
+        true => None,
+        false => Some(read_export_file(config)?),
+    };
+
+    let selected: Vec<&Chain> = config.selected_chains().collect();
+    let results = fetch_parallel(&selected, config.jobs, |chain| {
+        (fetcher.fetch_specs(chain), fetcher.fetch_metadata(chain))
+    });
+    fetcher.report_failures();
+    let mut fetched: HashMap<ChainPortalId, _> = selected
+        .iter()
+        .map(|c| c.portal_id())
+        .zip(results)
+        .collect();
 
     let mut export_specs = IndexMap::new();
     for chain in &config.chains {
+        if let Some(current) = &current {
+            if !config.filter.matches(chain) {
+                match current.get(&chain.portal_id()) {
+                    Some(spec) => {
+                        export_specs.insert(chain.portal_id(), spec.clone());
+                    }
+                    None => warn!("No current data found for {}, skipping", chain.portal_id()),
+                }
+                continue;
+            }
+        }
         info!("Collecting {} info...", chain.name);
-        let specs = match fetcher.fetch_specs(chain) {
+        let (specs, meta) = fetched
+            .remove(&chain.portal_id())
+            .expect("selected chains are fetched");
+        let specs = match specs {
             Ok(specs) => specs,
             Err(e) => {
                 if let Some(online_specs) = online.as_ref() {
@@ -37,7 +72,8 @@ pub(crate) fn export_specs(config: &AppConfig, fetcher: impl Fetcher) -> Result<
                 return Err(e);
             }
         };
-        let meta = fetcher.fetch_metadata(chain)?;
+        let meta = meta?;
+        check_account_type(chain, &meta.meta_values);
         let live_meta_version = meta.meta_values.version;
 
         let metadata_qrs =
@@ -57,12 +93,13 @@ pub(crate) fn export_specs(config: &AppConfig, fetcher: impl Fetcher) -> Result<
             ExportChainSpec {
                 title: chain.formatted_title(),
                 color: chain.color.clone(),
-                rpc_endpoint: chain.rpc_endpoints[0].clone(), // keep only the first one
+                rpc_endpoint: chain.rpc_endpoints[0].url.clone(), // keep only the first one, without headers
                 genesis_hash: format!("0x{}", hex::encode(specs.genesis_hash)),
                 unit: specs.unit,
                 logo: specs.logo,
                 decimals: specs.decimals,
                 base58prefix: specs.base58prefix,
+                encryption: chain.encryption(),
                 specs_qr: QrCode::from_qr_path(config, specs_qr)?,
                 latest_metadata: ReactAssetPath::from_fs_path(
                     &pointer_to_latest_meta,
@@ -70,6 +107,7 @@ pub(crate) fn export_specs(config: &AppConfig, fetcher: impl Fetcher) -> Result<
                 )?,
                 metadata_qr: export_live_metadata(config, metadata_qrs, &live_meta_version),
                 live_meta_version,
+                live_metadata_format: metadata_format(&meta.meta_values.meta),
                 relay_chain: chain.relay_chain.clone(),
             },
         );
@@ -94,10 +132,7 @@ fn export_live_metadata(
 
 // Create symlink to latest metadata qr
 fn update_pointer_to_latest_metadata(metadata_qr: &QrPath) -> Result<PathBuf> {
-    let latest_metadata_qr = metadata_qr.dir.join(format!(
-        "{}_metadata_latest.apng",
-        metadata_qr.file_name.chain
-    ));
+    let latest_metadata_qr = latest_metadata_path(&metadata_qr.dir, &metadata_qr.file_name.chain);
     if latest_metadata_qr.is_symlink() {
         fs::remove_file(&latest_metadata_qr).unwrap();
     }
@@ -113,11 +148,10 @@ mod tests {
     use definitions::crypto::Encryption;
     use definitions::metadata::MetaValues;
     use definitions::network_specs::NetworkSpecs;
-    use generate_message::helpers::MetaFetched;
     use sp_core::H256;
 
     use super::*;
-    use crate::config::Chain;
+    use crate::fetch::FetchedMeta;
 
     struct MockFetcher;
     impl Fetcher for MockFetcher {
@@ -140,8 +174,8 @@ mod tests {
             })
         }
 
-        fn fetch_metadata(&self, _chain: &Chain) -> Result<MetaFetched> {
-            Ok(MetaFetched {
+        fn fetch_metadata(&self, _chain: &Chain) -> Result<FetchedMeta> {
+            Ok(FetchedMeta {
                 meta_values: MetaValues {
                     name: "".to_string(),
                     version: 9,
@@ -149,8 +183,9 @@ mod tests {
                     warn_incomplete_extensions: false,
                     meta: vec![],
                 },
-                block_hash: H256::zero(),
                 genesis_hash: H256::zero(),
+                block_hash: H256::zero(),
+                block_number: None,
             })
         }
     }
@@ -1,13 +1,18 @@
 pub(crate) mod export;
 mod file;
 
+use std::path::PathBuf;
+
 use crate::collector::export::export_specs;
 use crate::collector::file::save_to_file;
 use crate::config::AppConfig;
-use crate::fetch::RpcFetcher;
+use crate::fetch::{node_fetcher, FileFetcher};
 
-pub(crate) fn collect(config: AppConfig) -> anyhow::Result<()> {
-    let specs = export_specs(&config, RpcFetcher)?;
+pub(crate) fn collect(config: AppConfig, dir: Option<PathBuf>) -> anyhow::Result<()> {
+    let specs = match dir {
+        Some(dir) => export_specs(&config, FileFetcher::new(dir))?,
+        None => export_specs(&config, node_fetcher(&config))?,
+    };
     save_to_file(&specs, config.data_file)?;
     Ok(())
 }
@@ -0,0 +1,81 @@
+use std::cell::RefCell;
+
+use log::{Level, Log, Metadata, Record};
+
+thread_local! {
+    // Records of the current thread held back by `capture`
+    static CAPTURED: RefCell<Option<Vec<CapturedRecord>>> = RefCell::new(None);
+}
+
+/// Log record held back to be logged later
+pub(crate) struct CapturedRecord {
+    level: Level,
+    target: String,
+    message: String,
+}
+
+/// Logger that holds back records of threads running `capture`,
+/// so that output of parallel work is not interleaved
+pub(crate) struct CapturingLogger {
+    inner: env_logger::Logger,
+}
+
+impl CapturingLogger {
+    // Install as the global logger
+    pub(crate) fn init(inner: env_logger::Logger) {
+        let max_level = inner.filter();
+        log::set_boxed_logger(Box::new(Self { inner })).expect("logger is initialized once");
+        log::set_max_level(max_level);
+    }
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+        let captured = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+            Some(records) => {
+                records.push(CapturedRecord {
+                    level: record.level(),
+                    target: record.target().to_string(),
+                    message: record.args().to_string(),
+                });
+                true
+            }
+            None => false,
+        });
+        if !captured {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+// Run `f` holding back log records of the current thread.
+// Records are only captured by `CapturingLogger`, other loggers get them right away
+pub(crate) fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<CapturedRecord>) {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(vec![]));
+    let result = f();
+    let records = CAPTURED.with(|captured| captured.borrow_mut().take());
+    (result, records.unwrap_or_default())
+}
+
+pub(crate) fn log_captured(records: Vec<CapturedRecord>) {
+    for record in records {
+        log::logger().log(
+            &Record::builder()
+                .level(record.level)
+                .target(&record.target)
+                .args(format_args!("{}", record.message))
+                .build(),
+        );
+    }
+}
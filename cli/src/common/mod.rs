@@ -1,3 +1,4 @@
 pub(crate) mod camera;
+pub(crate) mod logger;
 pub(crate) mod path;
 pub(crate) mod types;
@@ -1,6 +1,6 @@
 use std::convert::TryFrom;
 use std::fmt;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{bail, Context};
 
@@ -37,6 +37,11 @@ impl fmt::Display for QrPath {
     }
 }
 
+// Symlink pointing to the latest metadata QR of the chain
+pub(crate) fn latest_metadata_path(dir: &Path, chain: &str) -> PathBuf {
+    dir.join(format!("{chain}_metadata_latest.apng"))
+}
+
 #[derive(Debug, PartialEq, Clone, Hash, Eq)]
 pub(crate) enum ContentType {
     Metadata(u32),
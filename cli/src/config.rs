@@ -1,9 +1,35 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
-use std::{fmt, fs};
+use std::str::FromStr;
+use std::time::Duration;
+use std::{env, fmt, fs};
 
+use anyhow::{bail, ensure, Context};
+use definitions::crypto::Encryption;
 use log::debug;
-use serde::de::{self, value, SeqAccess, Visitor};
+use serde::de::{self, value, MapAccess, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
+use sp_core::H256;
+
+use crate::ethereum::is_ethereum;
+use crate::fetch::BlockId;
+use crate::updater::source::UpdateSource;
+
+const INCLUDE_KEY: &str = "include";
+const CHAINS_KEY: &str = "chains";
+const ENV_PREFIX: &str = "METADATA_CLI_";
+// Header values read from environment variables, e.g. `env:API_KEY`
+pub(crate) const ENV_REF_PREFIX: &str = "env:";
+// Shown instead of literal header values
+const REDACTED: &str = "<redacted>";
+// Scalar fields that can be overridden with `METADATA_CLI_<FIELD>` env variables
+const ENV_OVERRIDES: [&[&str]; 5] = [
+    &["data_file"],
+    &["public_dir"],
+    &["qr_dir"],
+    &["verifier", "name"],
+    &["verifier", "public_key"],
+];
 
 fn case_insensitive<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
@@ -13,24 +39,33 @@ where
     Ok(s.to_lowercase())
 }
 
-fn string_or_vec<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+fn endpoint_or_vec<'de, D>(deserializer: D) -> Result<Vec<RpcEndpoint>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    struct StringOrVec;
+    struct EndpointOrVec;
 
-    impl<'de> Visitor<'de> for StringOrVec {
-        type Value = Vec<String>;
+    impl<'de> Visitor<'de> for EndpointOrVec {
+        type Value = Vec<RpcEndpoint>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("string or list of strings")
+            formatter.write_str("endpoint or list of endpoints")
         }
 
         fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
         where
             E: de::Error,
         {
-            Ok(vec![s.to_owned()])
+            Ok(vec![RpcEndpoint::from(s)])
+        }
+
+        fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error>
+        where
+            M: MapAccess<'de>,
+        {
+            Ok(vec![Deserialize::deserialize(
+                value::MapAccessDeserializer::new(map),
+            )?])
         }
 
         fn visit_seq<S>(self, seq: S) -> Result<Self::Value, S::Error>
@@ -41,7 +76,7 @@ where
         }
     }
 
-    deserializer.deserialize_any(StringOrVec)
+    deserializer.deserialize_any(EndpointOrVec)
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -51,6 +86,24 @@ pub(crate) struct AppConfig {
     pub(crate) qr_dir: PathBuf,
     pub(crate) verifier: Verifier,
     pub(crate) chains: Vec<Chain>,
+    /// Number of chains fetched concurrently
+    #[serde(default = "jobs_default")]
+    pub(crate) jobs: usize,
+    #[serde(default)]
+    pub(crate) rpc: RpcSettings,
+    #[serde(default)]
+    pub(crate) cache: CacheSettings,
+    #[serde(skip)]
+    pub(crate) filter: ChainFilter,
+    #[serde(skip)]
+    pub(crate) recording: Option<Recording>,
+    /// Block to fetch data at instead of the finalized head
+    #[serde(skip)]
+    pub(crate) at: Option<BlockId>,
+}
+
+fn jobs_default() -> usize {
+    4
 }
 
 #[cfg(test)]
@@ -62,6 +115,12 @@ impl Default for AppConfig {
             qr_dir: PathBuf::from("qr"),
             verifier: Verifier::default(),
             chains: vec![Chain::default()],
+            jobs: jobs_default(),
+            rpc: RpcSettings::default(),
+            cache: CacheSettings::default(),
+            filter: ChainFilter::default(),
+            recording: None,
+            at: None,
         }
     }
 }
@@ -72,14 +131,184 @@ impl AppConfig {
         debug!("Loading config from: {}", abs_config_path.display());
         let root = abs_config_path.parent().unwrap();
 
-        let config_toml = fs::read_to_string(&path)?;
-        let mut config = toml::from_str::<AppConfig>(config_toml.as_str())?;
+        let mut layers = read_layers(&abs_config_path, &mut vec![])?;
+        apply_env_overrides(&mut layers, |name| env::var(name).ok());
+        let mut config: AppConfig = layers.try_into()?;
 
         config.public_dir = root.join(config.public_dir);
         config.data_file = root.join(config.data_file);
         config.qr_dir = root.join(config.qr_dir);
+        config.cache.dir = root.join(config.cache.dir);
         Ok(config)
     }
+
+    // Limit subcommands to the given chains. Unknown selectors are rejected
+    pub(crate) fn set_filter(&mut self, filter: ChainFilter) -> anyhow::Result<()> {
+        for portal_id in &filter.chains {
+            if !self.chains.iter().any(|c| &c.portal_id() == portal_id) {
+                bail!("unknown chain `{}`", portal_id);
+            }
+        }
+        for relay in &filter.relays {
+            if !self.chains.iter().any(|c| &c.name == relay) {
+                bail!("unknown relay chain `{}`", relay);
+            }
+        }
+        for tag in &filter.tags {
+            if !self.chains.iter().any(|c| c.tags.contains(tag)) {
+                bail!("no chains tagged `{}`", tag);
+            }
+        }
+        self.filter = filter;
+        Ok(())
+    }
+
+    // Fetch data at the given block. Only makes sense for a single chain
+    pub(crate) fn set_block(&mut self, at: BlockId) -> anyhow::Result<()> {
+        let selected = self.selected_chains().count();
+        ensure!(
+            selected == 1,
+            "fetching at a specific block requires a single chain, {} selected. Use --chain to select one",
+            selected
+        );
+        self.at = Some(at);
+        Ok(())
+    }
+
+    pub(crate) fn selected_chains(&self) -> impl Iterator<Item = &Chain> {
+        self.chains.iter().filter(|c| self.filter.matches(c))
+    }
+}
+
+// Read config file merged on top of its `include` list
+fn read_layers(path: &Path, parents: &mut Vec<PathBuf>) -> anyhow::Result<toml::Value> {
+    let path = fs::canonicalize(path).context(format!("{}", path.display()))?;
+    ensure!(
+        !parents.contains(&path),
+        "circular include of {}",
+        path.display()
+    );
+    debug!("Reading config layer: {}", path.display());
+
+    let mut layer: toml::Value =
+        toml::from_str(&fs::read_to_string(&path)?).context(format!("{}", path.display()))?;
+    let includes: Vec<PathBuf> = match layer.as_table_mut().unwrap().remove(INCLUDE_KEY) {
+        Some(includes) => includes.try_into()?,
+        None => vec![],
+    };
+
+    parents.push(path.clone());
+    let mut merged = toml::Value::Table(toml::value::Table::new());
+    for include in includes {
+        let include_layer = read_layers(&path.parent().unwrap().join(include), parents)?;
+        merge(&mut merged, include_layer);
+    }
+    parents.pop();
+
+    merge(&mut merged, layer);
+    Ok(merged)
+}
+
+// Tables are merged recursively, chains with the same portal id are merged together,
+// everything else is replaced by the overlay
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) if key == CHAINS_KEY => merge_chains(existing, value),
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn merge_chains(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Array(base), toml::Value::Array(overlay)) => {
+            for chain in overlay {
+                let existing = base
+                    .iter_mut()
+                    .find(|c| chain_key(c).is_some() && chain_key(c) == chain_key(&chain));
+                match existing {
+                    Some(existing) => merge(existing, chain),
+                    None => base.push(chain),
+                }
+            }
+        }
+        (base, overlay) => merge(base, overlay),
+    }
+}
+
+fn chain_key(chain: &toml::Value) -> Option<(String, Option<&str>)> {
+    let name = chain.get("name")?.as_str()?.to_lowercase();
+    Some((name, chain.get("relay_chain").and_then(|r| r.as_str())))
+}
+
+fn apply_env_overrides(config: &mut toml::Value, get_var: impl Fn(&str) -> Option<String>) {
+    for path in ENV_OVERRIDES {
+        let name = format!("{ENV_PREFIX}{}", path.join("_").to_uppercase());
+        let Some(value) = get_var(&name) else {
+            continue;
+        };
+        debug!("Overriding `{}` with {}", path.join("."), name);
+        let (field, tables) = path.split_last().unwrap();
+        let mut table = config.as_table_mut().unwrap();
+        for key in tables {
+            table = table
+                .entry(key.to_string())
+                .or_insert_with(|| toml::Value::Table(toml::value::Table::new()))
+                .as_table_mut()
+                .unwrap();
+        }
+        table.insert(field.to_string(), toml::Value::String(value));
+    }
+}
+
+// Print config file as is or the result of merging includes and env overrides
+pub(crate) fn show_config(path: &Path, config: &AppConfig, resolved: bool) -> anyhow::Result<()> {
+    match resolved {
+        true => print!("{}", resolved_toml(config)?),
+        false => print!("{}", fs::read_to_string(path)?),
+    }
+    Ok(())
+}
+
+// Merged config with literal header values hidden
+fn resolved_toml(config: &AppConfig) -> anyhow::Result<String> {
+    let mut config = config.clone();
+    for chain in &mut config.chains {
+        for endpoint in &mut chain.rpc_endpoints {
+            *endpoint = endpoint.redacted();
+        }
+    }
+    Ok(toml::to_string_pretty(&config)?)
+}
+
+/// Chains selected with `--chain`, `--relay` and `--tag`. Empty filter selects everything
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ChainFilter {
+    pub(crate) chains: Vec<String>,
+    pub(crate) relays: Vec<String>,
+    pub(crate) tags: Vec<String>,
+}
+
+impl ChainFilter {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.chains.is_empty() && self.relays.is_empty() && self.tags.is_empty()
+    }
+
+    pub(crate) fn matches(&self, chain: &Chain) -> bool {
+        let relay = chain.relay_chain.as_ref().unwrap_or(&chain.name);
+        (self.chains.is_empty() || self.chains.contains(&chain.portal_id()))
+            && (self.relays.is_empty() || self.relays.contains(relay))
+            && (self.tags.is_empty() || self.tags.iter().any(|t| chain.tags.contains(t)))
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -103,6 +332,256 @@ pub(crate) struct GithubRepo {
     pub(crate) owner: String,
     pub(crate) repo: String,
     pub(crate) genesis_hash: String,
+    /// Regex for release asset names with named `chain` and `version` groups
+    pub(crate) asset_pattern: Option<String>,
+    /// Chain name in release asset names if it differs from the chain name
+    pub(crate) asset_chain: Option<String>,
+    #[serde(default)]
+    pub(crate) channel: ReleaseChannel,
+}
+
+/// Releases the GitHub source takes runtimes from
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ReleaseChannel {
+    /// Published releases only
+    #[default]
+    Latest,
+    /// Pre-releases too, e.g. for testnets
+    Prerelease,
+}
+
+/// JSON-RPC endpoint of a node: either a plain url or a table with `url` and `headers`
+#[derive(Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "RawEndpoint", into = "RawEndpoint")]
+pub(crate) struct RpcEndpoint {
+    pub(crate) url: String,
+    /// Header values starting with `env:` are read from the environment variable
+    pub(crate) headers: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RawEndpoint {
+    Url(String),
+    WithHeaders {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+}
+
+impl From<RawEndpoint> for RpcEndpoint {
+    fn from(raw: RawEndpoint) -> Self {
+        match raw {
+            RawEndpoint::Url(url) => Self::from(url.as_str()),
+            RawEndpoint::WithHeaders { url, headers } => Self { url, headers },
+        }
+    }
+}
+
+impl From<RpcEndpoint> for RawEndpoint {
+    fn from(endpoint: RpcEndpoint) -> Self {
+        match endpoint.headers.is_empty() {
+            true => Self::Url(endpoint.url),
+            false => Self::WithHeaders {
+                url: endpoint.url,
+                headers: endpoint.headers,
+            },
+        }
+    }
+}
+
+impl From<&str> for RpcEndpoint {
+    fn from(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+// Only the url is shown, header values may be secrets
+impl fmt::Display for RpcEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.url)
+    }
+}
+
+impl fmt::Debug for RpcEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RpcEndpoint")
+            .field("url", &self.url)
+            .field("headers", &self.redacted().headers)
+            .finish()
+    }
+}
+
+impl RpcEndpoint {
+    // Copy with literal header values hidden, `env:` references are kept
+    pub(crate) fn redacted(&self) -> Self {
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| match value.starts_with(ENV_REF_PREFIX) {
+                true => (name.clone(), value.clone()),
+                false => (name.clone(), REDACTED.to_string()),
+            })
+            .collect();
+        Self {
+            url: self.url.clone(),
+            headers,
+        }
+    }
+
+    // Header values with `env:` references replaced by the variables
+    pub(crate) fn resolve_headers<F>(&self, env_var: F) -> anyhow::Result<Vec<(String, String)>>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.headers
+            .iter()
+            .map(|(name, value)| match value.strip_prefix(ENV_REF_PREFIX) {
+                Some(var) => match env_var(var) {
+                    Some(value) => Ok((name.clone(), value)),
+                    None => bail!(
+                        "environment variable {} required by `{}` header of {} is not set",
+                        var,
+                        name,
+                        self.url
+                    ),
+                },
+                None => Ok((name.clone(), value.clone())),
+            })
+            .collect()
+    }
+}
+
+/// RPC settings. Chain settings take precedence over the global ones
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub(crate) struct RpcSettings {
+    /// Query all endpoints and require this many of them to return the same data
+    pub(crate) quorum: Option<usize>,
+    /// Seconds to wait for a connection to an endpoint
+    pub(crate) connect_timeout: Option<u64>,
+    /// Seconds to wait for a response to a single request
+    pub(crate) request_timeout: Option<u64>,
+    /// How many times to retry a failed endpoint
+    pub(crate) retries: Option<u32>,
+    /// Milliseconds to wait before the first retry. Doubled after every attempt
+    pub(crate) backoff: Option<u64>,
+}
+
+impl RpcSettings {
+    pub(crate) fn merge(&self, chain: &RpcSettings) -> RpcSettings {
+        RpcSettings {
+            quorum: chain.quorum.or(self.quorum),
+            connect_timeout: chain.connect_timeout.or(self.connect_timeout),
+            request_timeout: chain.request_timeout.or(self.request_timeout),
+            retries: chain.retries.or(self.retries),
+            backoff: chain.backoff.or(self.backoff),
+        }
+    }
+
+    pub(crate) fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout.unwrap_or(10))
+    }
+
+    pub(crate) fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout.unwrap_or(60))
+    }
+
+    pub(crate) fn retries(&self) -> u32 {
+        self.retries.unwrap_or(2)
+    }
+
+    // Delay before the given retry, starting from 1
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let base = self.backoff.unwrap_or(1000);
+        Duration::from_millis(base.saturating_mul(1 << (attempt - 1).min(16)))
+    }
+}
+
+/// On-disk cache of fetched specs and metadata
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub(crate) struct CacheSettings {
+    #[serde(default = "cache_dir_default")]
+    pub(crate) dir: PathBuf,
+    /// Seconds after which cached data is fetched again
+    #[serde(default = "cache_ttl_default")]
+    pub(crate) ttl: u64,
+    #[serde(skip)]
+    pub(crate) mode: CacheMode,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            dir: cache_dir_default(),
+            ttl: cache_ttl_default(),
+            mode: CacheMode::default(),
+        }
+    }
+}
+
+fn cache_dir_default() -> PathBuf {
+    PathBuf::from(".cache")
+}
+
+fn cache_ttl_default() -> u64 {
+    600
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum CacheMode {
+    /// Use cached data until it expires
+    #[default]
+    Default,
+    /// Use cached data regardless of its age and never touch the network
+    Offline,
+    /// Ignore cached data and fetch everything again
+    Refresh,
+}
+
+/// Directory to save RPC responses to or to serve them from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Recording {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+/// Signing algorithm of the chain accounts
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum EncryptionType {
+    #[default]
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+    Ethereum,
+}
+
+impl fmt::Display for EncryptionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            EncryptionType::Sr25519 => "sr25519",
+            EncryptionType::Ed25519 => "ed25519",
+            EncryptionType::Ecdsa => "ecdsa",
+            EncryptionType::Ethereum => "ethereum",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl From<EncryptionType> for Encryption {
+    fn from(encryption: EncryptionType) -> Self {
+        match encryption {
+            EncryptionType::Sr25519 => Encryption::Sr25519,
+            EncryptionType::Ed25519 => Encryption::Ed25519,
+            EncryptionType::Ecdsa => Encryption::Ecdsa,
+            EncryptionType::Ethereum => Encryption::Ethereum,
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -112,12 +591,24 @@ pub(crate) struct Chain {
     pub(crate) title: Option<String>,
     #[serde(default = "color_default")]
     pub(crate) color: String,
-    #[serde(alias = "rpc_endpoint", deserialize_with = "string_or_vec")]
-    pub(crate) rpc_endpoints: Vec<String>,
+    #[serde(alias = "rpc_endpoint", deserialize_with = "endpoint_or_vec")]
+    pub(crate) rpc_endpoints: Vec<RpcEndpoint>,
     pub(crate) token_unit: Option<String>,
     pub(crate) token_decimals: Option<u8>,
+    /// Fetched data of any other network is rejected
+    pub(crate) genesis_hash: Option<String>,
+    /// Metadata format version to fetch instead of the newest parsable one
+    pub(crate) metadata_version: Option<u32>,
     pub(crate) github_release: Option<GithubRepo>,
     pub(crate) relay_chain: Option<String>,
+    pub(crate) encryption: Option<EncryptionType>,
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
+    #[serde(default)]
+    pub(crate) rpc: RpcSettings,
+    /// Sources `update` tries in order. Node only if empty
+    #[serde(default)]
+    pub(crate) update_sources: Vec<UpdateSource>,
 }
 
 impl Chain {
@@ -135,9 +626,25 @@ impl Chain {
         }
         title.to_owned()
     }
+
+    // Pinned genesis hash. Checked by config validation
+    pub(crate) fn genesis_hash(&self) -> Option<H256> {
+        self.genesis_hash
+            .as_ref()
+            .and_then(|hash| H256::from_str(hash.trim_start_matches("0x")).ok())
+    }
+
+    // Configured encryption or the default one for known Ethereum-like chains
+    pub(crate) fn encryption(&self) -> EncryptionType {
+        match self.encryption {
+            Some(encryption) => encryption,
+            None if is_ethereum(&self.name) => EncryptionType::Ethereum,
+            None => EncryptionType::Sr25519,
+        }
+    }
 }
 
-fn color_default() -> String {
+pub(crate) fn color_default() -> String {
     "#000000".to_string()
 }
 
@@ -148,11 +655,277 @@ impl Default for Chain {
             name: "polkadot".to_string(),
             title: None,
             color: color_default(),
-            rpc_endpoints: vec!["wss://example.com".to_string()],
+            rpc_endpoints: vec![RpcEndpoint::from("wss://example.com")],
             token_unit: None,
             token_decimals: None,
+            genesis_hash: None,
+            metadata_version: None,
             github_release: None,
             relay_chain: None,
+            encryption: None,
+            tags: vec![],
+            rpc: RpcSettings::default(),
+            update_sources: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chains() -> Vec<Chain> {
+        vec![
+            Chain {
+                tags: vec!["relay".to_string()],
+                ..Chain::default()
+            },
+            Chain {
+                name: "statemint".to_string(),
+                relay_chain: Some("polkadot".to_string()),
+                ..Chain::default()
+            },
+            Chain {
+                name: "kusama".to_string(),
+                tags: vec!["relay".to_string()],
+                ..Chain::default()
+            },
+        ]
+    }
+
+    fn selected(filter: ChainFilter) -> Vec<String> {
+        let mut config = AppConfig {
+            chains: chains(),
+            ..Default::default()
+        };
+        config.set_filter(filter).unwrap();
+        config.selected_chains().map(|c| c.portal_id()).collect()
+    }
+
+    #[test]
+    fn empty_filter_selects_all() {
+        assert_eq!(selected(ChainFilter::default()).len(), 3);
+    }
+
+    #[test]
+    fn select_by_relay() {
+        let filter = ChainFilter {
+            relays: vec!["polkadot".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(selected(filter), vec!["polkadot", "polkadot-statemint"]);
+    }
+
+    #[test]
+    fn select_by_chain_and_tag() {
+        let filter = ChainFilter {
+            chains: vec!["polkadot-statemint".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(selected(filter), vec!["polkadot-statemint"]);
+
+        let filter = ChainFilter {
+            tags: vec!["relay".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(selected(filter), vec!["polkadot", "kusama"]);
+    }
+
+    #[test]
+    fn merge_included_chains() {
+        let mut base: toml::Value = toml::from_str(
+            r##"
+            qr_dir = "qr"
+            [verifier]
+            name = "Parity"
+            [[chains]]
+            name = "Polkadot"
+            color = "#e6007a"
+            [[chains]]
+            name = "statemint"
+            relay_chain = "polkadot"
+            "##,
+        )
+        .unwrap();
+        let overlay: toml::Value = toml::from_str(
+            r##"
+            qr_dir = "local/qr"
+            [verifier]
+            public_key = "123"
+            [[chains]]
+            name = "polkadot"
+            color = "#000000"
+            [[chains]]
+            name = "statemine"
+            relay_chain = "kusama"
+            "##,
+        )
+        .unwrap();
+        merge(&mut base, overlay);
+
+        let expected: toml::Value = toml::from_str(
+            r##"
+            qr_dir = "local/qr"
+            [verifier]
+            name = "Parity"
+            public_key = "123"
+            [[chains]]
+            name = "polkadot"
+            color = "#000000"
+            [[chains]]
+            name = "statemint"
+            relay_chain = "polkadot"
+            [[chains]]
+            name = "statemine"
+            relay_chain = "kusama"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn override_with_env() {
+        let mut config: toml::Value = toml::from_str("qr_dir = \"qr\"").unwrap();
+        apply_env_overrides(&mut config, |name| match name {
+            "METADATA_CLI_QR_DIR" => Some("other".to_string()),
+            "METADATA_CLI_VERIFIER_NAME" => Some("Fork".to_string()),
+            _ => None,
+        });
+
+        let expected: toml::Value =
+            toml::from_str("qr_dir = \"other\"\n[verifier]\nname = \"Fork\"").unwrap();
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn reject_unknown_chain() {
+        let mut config = AppConfig::default();
+        let filter = ChainFilter {
+            chains: vec!["unknown".to_string()],
+            ..Default::default()
+        };
+        assert!(config.set_filter(filter).is_err());
+    }
+
+    #[test]
+    fn chain_rpc_settings_take_precedence() {
+        let global = RpcSettings {
+            retries: Some(5),
+            backoff: Some(100),
+            ..Default::default()
+        };
+        let chain = RpcSettings {
+            retries: Some(1),
+            ..Default::default()
+        };
+        let settings = global.merge(&chain);
+        assert_eq!(settings.retries(), 1);
+        assert_eq!(settings.backoff(1), Duration::from_millis(100));
+        assert_eq!(settings.backoff(3), Duration::from_millis(400));
+        assert_eq!(settings.request_timeout(), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn parse_endpoints_with_headers() {
+        #[derive(Deserialize)]
+        struct Endpoints {
+            #[serde(deserialize_with = "endpoint_or_vec")]
+            rpc_endpoint: Vec<RpcEndpoint>,
+        }
+        let parsed: Endpoints = toml::from_str(
+            r#"rpc_endpoint = ["wss://a.io", { url = "https://b.io", headers = { Authorization = "env:B_KEY" } }]"#,
+        )
+        .unwrap();
+        assert_eq!(parsed.rpc_endpoint[0], RpcEndpoint::from("wss://a.io"));
+        assert_eq!(parsed.rpc_endpoint[1].url, "https://b.io");
+
+        let headers = parsed.rpc_endpoint[1]
+            .resolve_headers(|var| (var == "B_KEY").then(|| "Bearer secret".to_string()))
+            .unwrap();
+        assert_eq!(
+            headers,
+            vec![("Authorization".to_string(), "Bearer secret".to_string())]
+        );
+        assert!(parsed.rpc_endpoint[1].resolve_headers(|_| None).is_err());
+        assert_eq!(parsed.rpc_endpoint[1].to_string(), "https://b.io");
+    }
+
+    #[test]
+    fn redact_header_values() {
+        let endpoint = RpcEndpoint {
+            url: "https://a.io".to_string(),
+            headers: BTreeMap::from([
+                ("Authorization".to_string(), "Bearer secret".to_string()),
+                ("X-Api-Key".to_string(), "env:A_KEY".to_string()),
+            ]),
+        };
+        let redacted = endpoint.redacted();
+        assert_eq!(redacted.headers["Authorization"], REDACTED);
+        assert_eq!(redacted.headers["X-Api-Key"], "env:A_KEY");
+
+        let debug = format!("{endpoint:?}");
+        assert!(!debug.contains("secret"), "{debug}");
+        assert!(debug.contains("env:A_KEY"), "{debug}");
+
+        let config = AppConfig {
+            chains: vec![Chain {
+                rpc_endpoints: vec![endpoint],
+                ..Chain::default()
+            }],
+            ..AppConfig::default()
+        };
+        assert!(!format!("{config:?}").contains("secret"));
+        let resolved = resolved_toml(&config).unwrap();
+        assert!(!resolved.contains("secret"), "{resolved}");
+        assert!(resolved.contains("env:A_KEY"), "{resolved}");
+    }
+
+    #[test]
+    fn configured_encryption_takes_precedence() {
+        let chain = |name: &str, encryption| Chain {
+            name: name.to_string(),
+            encryption,
+            ..Chain::default()
+        };
+        assert_eq!(
+            chain("polkadot", None).encryption(),
+            EncryptionType::Sr25519
+        );
+        // Known Ethereum chains fall back to the hard-coded list
+        assert_eq!(
+            chain("moonbeam", None).encryption(),
+            EncryptionType::Ethereum
+        );
+        assert_eq!(
+            chain("moonbeam", Some(EncryptionType::Ecdsa)).encryption(),
+            EncryptionType::Ecdsa
+        );
+        assert_eq!(
+            chain("new-evm", Some(EncryptionType::Ethereum)).encryption(),
+            EncryptionType::Ethereum
+        );
+
+        let parsed: Chain = toml::from_str(
+            "name = \"new-evm\"\nrpc_endpoint = \"wss://a.io\"\nencryption = \"ethereum\"",
+        )
+        .unwrap();
+        assert_eq!(parsed.encryption(), EncryptionType::Ethereum);
+    }
+
+    #[test]
+    fn convert_encryption() {
+        for encryption in [
+            EncryptionType::Sr25519,
+            EncryptionType::Ed25519,
+            EncryptionType::Ecdsa,
+            EncryptionType::Ethereum,
+        ] {
+            assert_eq!(Encryption::from(encryption).show(), encryption.to_string());
         }
+        assert!(matches!(
+            Encryption::from(EncryptionType::Ethereum),
+            Encryption::Ethereum
+        ));
     }
 }
@@ -0,0 +1,373 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::bail;
+use log::error;
+use regex::Regex;
+use reqwest::Url;
+use sp_core::H256;
+
+use crate::config::{AppConfig, Chain, RpcEndpoint, ENV_REF_PREFIX};
+use crate::fetch::PARSABLE_METADATA_VERSIONS;
+
+const RPC_SCHEMES: [&str; 4] = ["ws", "wss", "http", "https"];
+
+/// A single semantic problem found in `config.toml`
+#[derive(Debug, PartialEq)]
+pub(crate) struct ConfigProblem {
+    pub(crate) chain: Option<String>,
+    pub(crate) field: String,
+    pub(crate) message: String,
+}
+
+impl ConfigProblem {
+    fn new(chain: Option<&Chain>, field: &str, message: String) -> Self {
+        Self {
+            chain: chain.map(|c| c.portal_id()),
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.chain {
+            Some(chain) => write!(f, "[{}] `{}`: {}", chain, self.field, self.message),
+            None => write!(f, "`{}`: {}", self.field, self.message),
+        }
+    }
+}
+
+// Log every problem found in the config and fail if there is at least one
+pub(crate) fn validate_config(config: &AppConfig) -> anyhow::Result<()> {
+    let problems = check_config(config);
+    if problems.is_empty() {
+        return Ok(());
+    }
+    for problem in &problems {
+        error!("❌ {}", problem);
+    }
+    bail!("config is invalid: {} problem(s) found", problems.len())
+}
+
+pub(crate) fn check_config(config: &AppConfig) -> Vec<ConfigProblem> {
+    let mut problems = vec![];
+
+    if let Err(e) = check_hex(&config.verifier.public_key, &[32, 33], false) {
+        problems.push(ConfigProblem::new(None, "verifier.public_key", e));
+    }
+    if config.jobs == 0 {
+        problems.push(ConfigProblem::new(
+            None,
+            "jobs",
+            "must be greater than 0".to_string(),
+        ));
+    }
+
+    let chain_names: HashSet<&str> = config.chains.iter().map(|c| c.name.as_str()).collect();
+    let mut portal_ids: HashMap<String, usize> = HashMap::new();
+    for chain in &config.chains {
+        *portal_ids.entry(chain.portal_id()).or_default() += 1;
+    }
+
+    for chain in &config.chains {
+        let portal_id = chain.portal_id();
+        if portal_ids[&portal_id] > 1 {
+            problems.push(ConfigProblem::new(
+                Some(chain),
+                "name",
+                format!("portal id `{portal_id}` is used by more than one chain"),
+            ));
+        }
+        if let Some(relay) = &chain.relay_chain {
+            if !chain_names.contains(relay.as_str()) {
+                problems.push(ConfigProblem::new(
+                    Some(chain),
+                    "relay_chain",
+                    format!("`{relay}` is not a configured chain"),
+                ));
+            }
+        }
+        if !is_valid_color(&chain.color) {
+            problems.push(ConfigProblem::new(
+                Some(chain),
+                "color",
+                format!("`{}` is not a #RRGGBB color", chain.color),
+            ));
+        }
+        if chain.rpc_endpoints.is_empty() {
+            problems.push(ConfigProblem::new(
+                Some(chain),
+                "rpc_endpoints",
+                "at least one endpoint is required".to_string(),
+            ));
+        }
+        for endpoint in &chain.rpc_endpoints {
+            if let Err(e) = check_rpc_endpoint(endpoint) {
+                problems.push(ConfigProblem::new(Some(chain), "rpc_endpoints", e));
+            }
+        }
+        let rpc = config.rpc.merge(&chain.rpc);
+        for (field, timeout) in [
+            ("rpc.connect_timeout", rpc.connect_timeout),
+            ("rpc.request_timeout", rpc.request_timeout),
+        ] {
+            if timeout == Some(0) {
+                problems.push(ConfigProblem::new(
+                    Some(chain),
+                    field,
+                    "must be greater than 0".to_string(),
+                ));
+            }
+        }
+        match rpc.quorum {
+            Some(0) => problems.push(ConfigProblem::new(
+                Some(chain),
+                "rpc.quorum",
+                "must be greater than 0".to_string(),
+            )),
+            Some(quorum) if quorum > chain.rpc_endpoints.len() => {
+                problems.push(ConfigProblem::new(
+                    Some(chain),
+                    "rpc.quorum",
+                    format!(
+                        "{} endpoints required, but only {} configured",
+                        quorum,
+                        chain.rpc_endpoints.len()
+                    ),
+                ))
+            }
+            _ => (),
+        }
+        if let Some(genesis_hash) = &chain.genesis_hash {
+            if let Err(e) = check_hex(genesis_hash, &[32], true) {
+                problems.push(ConfigProblem::new(Some(chain), "genesis_hash", e));
+            }
+        }
+        if let Some(version) = chain.metadata_version {
+            if !PARSABLE_METADATA_VERSIONS.contains(&version) {
+                problems.push(ConfigProblem::new(
+                    Some(chain),
+                    "metadata_version",
+                    format!(
+                        "V{version} can not be parsed, expected one of {:?}",
+                        PARSABLE_METADATA_VERSIONS
+                    ),
+                ));
+            }
+        }
+        if let Some(github) = &chain.github_release {
+            if let Some(pattern) = &github.asset_pattern {
+                if let Err(e) = check_asset_pattern(pattern) {
+                    problems.push(ConfigProblem::new(
+                        Some(chain),
+                        "github_release.asset_pattern",
+                        e,
+                    ));
+                }
+            }
+            if let Err(e) = check_hex(&github.genesis_hash, &[32], true) {
+                problems.push(ConfigProblem::new(
+                    Some(chain),
+                    "github_release.genesis_hash",
+                    e,
+                ));
+            } else if let Some(genesis_hash) = chain.genesis_hash() {
+                let github_hash = H256::from_str(github.genesis_hash.trim_start_matches("0x"));
+                if github_hash.ok() != Some(genesis_hash) {
+                    problems.push(ConfigProblem::new(
+                        Some(chain),
+                        "github_release.genesis_hash",
+                        "differs from the chain `genesis_hash`".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+    problems
+}
+
+fn is_valid_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+fn check_rpc_endpoint(endpoint: &RpcEndpoint) -> Result<(), String> {
+    let url =
+        Url::parse(&endpoint.url).map_err(|e| format!("`{endpoint}` is not a valid url: {e}"))?;
+    if !RPC_SCHEMES.contains(&url.scheme()) {
+        return Err(format!(
+            "`{endpoint}` has unsupported scheme `{}`, expected one of {}",
+            url.scheme(),
+            RPC_SCHEMES.join(", ")
+        ));
+    }
+    for (name, value) in &endpoint.headers {
+        if value.strip_prefix(ENV_REF_PREFIX) == Some("") {
+            return Err(format!(
+                "`{name}` header of `{endpoint}` has no environment variable name"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_asset_pattern(pattern: &str) -> Result<(), String> {
+    let regex =
+        Regex::new(pattern).map_err(|e| format!("`{pattern}` is not a valid regex: {e}"))?;
+    for group in ["chain", "version"] {
+        if !regex.capture_names().flatten().any(|name| name == group) {
+            return Err(format!("`{pattern}` has no `{group}` named group"));
+        }
+    }
+    Ok(())
+}
+
+fn check_hex(value: &str, lengths: &[usize], allow_prefix: bool) -> Result<(), String> {
+    let stripped = match value.strip_prefix("0x") {
+        Some(_) if !allow_prefix => return Err(format!("`{value}` must not start with 0x")),
+        Some(s) => s,
+        None => value,
+    };
+    let bytes = hex::decode(stripped).map_err(|e| format!("`{value}` is not valid hex: {e}"))?;
+    if !lengths.contains(&bytes.len()) {
+        return Err(format!(
+            "`{value}` is {} bytes long, expected {:?}",
+            bytes.len(),
+            lengths
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GithubRepo, ReleaseChannel, Verifier};
+
+    fn valid_config() -> AppConfig {
+        AppConfig {
+            verifier: Verifier {
+                name: "Test Verifier".to_string(),
+                public_key: "c46a22b9da19540a77cbde23197e5fd90485c72b4ecf3c599ecca6998f39bd57"
+                    .to_string(),
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn accepts_valid_config() {
+        assert!(check_config(&valid_config()).is_empty());
+    }
+
+    #[test]
+    fn reports_duplicated_portal_id() {
+        let mut config = valid_config();
+        config.chains = vec![Chain::default(), Chain::default()];
+
+        let problems = check_config(&config);
+        assert_eq!(problems.len(), 2);
+        assert!(problems.iter().all(|p| p.field == "name"));
+    }
+
+    #[test]
+    fn reports_unknown_relay_chain() {
+        let mut config = valid_config();
+        config.chains.push(Chain {
+            name: "statemine".to_string(),
+            relay_chain: Some("kusama".to_string()),
+            ..Chain::default()
+        });
+
+        let problems = check_config(&config);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].chain, Some("kusama-statemine".to_string()));
+        assert_eq!(problems[0].field, "relay_chain");
+    }
+
+    #[test]
+    fn label_problems_by_portal_id() {
+        let mut config = valid_config();
+        for relay in ["kusama", "westend"] {
+            config.chains.push(Chain {
+                name: relay.to_string(),
+                ..Chain::default()
+            });
+            config.chains.push(Chain {
+                name: "statemine".to_string(),
+                relay_chain: Some(relay.to_string()),
+                color: "red".to_string(),
+                ..Chain::default()
+            });
+        }
+
+        let labels: Vec<_> = check_config(&config)
+            .into_iter()
+            .filter_map(|p| p.chain)
+            .collect();
+        assert_eq!(labels, vec!["kusama-statemine", "westend-statemine"]);
+    }
+
+    #[test]
+    fn reports_every_chain_problem() {
+        let mut config = valid_config();
+        config.verifier.public_key = "0x123".to_string();
+        config.chains = vec![Chain {
+            color: "red".to_string(),
+            rpc_endpoints: vec!["ftp://example.com".into()],
+            github_release: Some(GithubRepo {
+                owner: "paritytech".to_string(),
+                repo: "polkadot".to_string(),
+                genesis_hash: "0xnothex".to_string(),
+                asset_pattern: None,
+                asset_chain: None,
+                channel: ReleaseChannel::default(),
+            }),
+            ..Chain::default()
+        }];
+
+        let fields: Vec<String> = check_config(&config).into_iter().map(|p| p.field).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "verifier.public_key",
+                "color",
+                "rpc_endpoints",
+                "github_release.genesis_hash"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_conflicting_genesis_hash() {
+        let mut config = valid_config();
+        config.chains = vec![Chain {
+            genesis_hash: Some(format!("0x{}", "11".repeat(32))),
+            github_release: Some(GithubRepo {
+                owner: "paritytech".to_string(),
+                repo: "polkadot".to_string(),
+                genesis_hash: format!("0x{}", "22".repeat(32)),
+                asset_pattern: None,
+                asset_chain: None,
+                channel: ReleaseChannel::default(),
+            }),
+            ..Chain::default()
+        }];
+
+        let problems = check_config(&config);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].field, "github_release.genesis_hash");
+    }
+
+    #[test]
+    fn reports_asset_pattern_without_groups() {
+        assert!(check_asset_pattern(r"^(?P<chain>[\w-]+)_v(?P<version>\d+)\.wasm$").is_ok());
+        assert!(check_asset_pattern(r"^(?P<chain>[\w-]+)\.wasm$").is_err());
+        assert!(check_asset_pattern(r"^(?P<chain>[").is_err());
+    }
+}
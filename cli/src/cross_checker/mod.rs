@@ -0,0 +1,145 @@
+use anyhow::{bail, Result};
+use blake2_rfc::blake2b::blake2b;
+use definitions::metadata::MetaValues;
+use log::{error, info, warn};
+
+use crate::config::{AppConfig, Chain};
+use crate::fetch::{metadata_format, Fetcher};
+use crate::updater::github::fetch_latest_runtime;
+use crate::updater::wasm::{
+    check_runtime_version, decompress_wasm, download_wasm, meta_values_from_wasm_bytes,
+};
+
+#[derive(Debug, PartialEq)]
+enum CrossCheck {
+    Match,
+    // Node and release run different runtimes, nothing to compare
+    DifferentVersions {
+        wasm: u32,
+        node: u32,
+    },
+    Mismatch {
+        wasm_hash: String,
+        node_hash: String,
+    },
+}
+
+// Compare metadata of the latest GitHub release wasm with metadata fetched from the node.
+// Fails if any chain returns different metadata for the same spec version
+pub(crate) fn cross_check(config: AppConfig, fetcher: impl Fetcher + Sync) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut failed = vec![];
+    for chain in config.selected_chains() {
+        if chain.github_release.is_none() {
+            continue;
+        }
+        info!("🔍 Cross-checking {}", chain.name);
+        match cross_check_chain(&runtime, chain, &fetcher) {
+            Ok(Some(CrossCheck::Match)) => info!("✅ Metadata is the same"),
+            Ok(Some(CrossCheck::DifferentVersions { wasm, node })) => info!(
+                "↪️ Release has spec version {}, node has {}, skipping",
+                wasm, node
+            ),
+            Ok(Some(CrossCheck::Mismatch {
+                wasm_hash,
+                node_hash,
+            })) => {
+                error!(
+                    "❌ Metadata differs: release wasm {}, node {}",
+                    wasm_hash, node_hash
+                );
+                failed.push(chain.portal_id());
+            }
+            Ok(None) => warn!("🤨 No releases found"),
+            Err(e) => {
+                error!("❌ Failed to cross-check: {:#}", e);
+                failed.push(chain.portal_id());
+            }
+        }
+    }
+    fetcher.report_failures();
+    if !failed.is_empty() {
+        bail!("cross-check failed for {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+fn cross_check_chain(
+    runtime: &tokio::runtime::Runtime,
+    chain: &Chain,
+    fetcher: &impl Fetcher,
+) -> Result<Option<CrossCheck>> {
+    let github_repo = chain.github_release.as_ref().expect("checked by caller");
+    let wasm = match runtime.block_on(fetch_latest_runtime(github_repo, &chain.name))? {
+        Some(wasm) => wasm,
+        None => return Ok(None),
+    };
+    let wasm_bytes = runtime.block_on(download_wasm(&wasm))?;
+    let wasm_meta = meta_values_from_wasm_bytes(&decompress_wasm(&wasm_bytes)?)?;
+    check_runtime_version(&wasm_meta, &chain.name, &wasm)?;
+
+    // Fetch from the node in the same format the wasm provides
+    let node_chain = Chain {
+        metadata_version: metadata_format(&wasm_meta.meta).map(u32::from),
+        ..chain.clone()
+    };
+    let node_meta = fetcher.fetch_metadata(&node_chain)?;
+    Ok(Some(compare(&wasm_meta, &node_meta.meta_values)))
+}
+
+fn compare(wasm: &MetaValues, node: &MetaValues) -> CrossCheck {
+    if wasm.version != node.version {
+        return CrossCheck::DifferentVersions {
+            wasm: wasm.version,
+            node: node.version,
+        };
+    }
+    let wasm_hash = meta_hash(&wasm.meta);
+    let node_hash = meta_hash(&node.meta);
+    match wasm_hash == node_hash {
+        true => CrossCheck::Match,
+        false => CrossCheck::Mismatch {
+            wasm_hash,
+            node_hash,
+        },
+    }
+}
+
+fn meta_hash(meta: &[u8]) -> String {
+    format!("0x{}", hex::encode(blake2b(32, &[], meta).as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta_values(version: u32, meta: &[u8]) -> MetaValues {
+        MetaValues {
+            name: "polkadot".to_string(),
+            version,
+            optional_base58prefix: None,
+            warn_incomplete_extensions: false,
+            meta: meta.to_vec(),
+        }
+    }
+
+    #[test]
+    fn compare_same_spec_version_only() {
+        let wasm = meta_values(9370, b"meta\x0e1");
+        assert_eq!(
+            compare(&wasm, &meta_values(9370, b"meta\x0e1")),
+            CrossCheck::Match
+        );
+        assert_eq!(
+            compare(&wasm, &meta_values(9360, b"meta\x0e2")),
+            CrossCheck::DifferentVersions {
+                wasm: 9370,
+                node: 9360
+            }
+        );
+        assert!(matches!(
+            compare(&wasm, &meta_values(9370, b"meta\x0e2")),
+            CrossCheck::Mismatch { .. }
+        ));
+    }
+}
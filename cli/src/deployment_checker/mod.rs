@@ -4,14 +4,14 @@ use anyhow::Result;
 use log::{info, warn};
 
 use crate::collector::export::export_specs;
-use crate::fetch::{fetch_deployed_data, RpcFetcher};
+use crate::fetch::{fetch_deployed_data, node_fetcher};
 use crate::AppConfig;
 
 // Check whether the deployment is up to date.
 // Exit code 12 if re-deploy is required
 pub(crate) fn check_deployment(config: AppConfig) -> Result<()> {
     let online = fetch_deployed_data(&config)?;
-    let local = export_specs(&config, RpcFetcher);
+    let local = export_specs(&config, node_fetcher(&config));
 
     if let Err(e) = local {
         warn!("Error exporting specs: {}", e);
@@ -1,3 +1,14 @@
+use anyhow::Result;
+use definitions::metadata::MetaValues;
+use frame_metadata::v14::StorageEntryType;
+use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+use log::warn;
+use parity_scale_codec::Decode;
+use scale_info::{PortableRegistry, TypeDef, TypeDefPrimitive};
+
+use crate::config::{Chain, EncryptionType};
+
+// Fallback list of Ethereum-like chains without `encryption` set in config
 const ETHEREUM_CHAINS: [&str; 10] = [
     "jaz",
     "moonbase",
@@ -11,6 +22,189 @@ const ETHEREUM_CHAINS: [&str; 10] = [
     "root",
 ];
 
+const ETHEREUM_ACCOUNT_LEN: usize = 20;
+const SUBSTRATE_ACCOUNT_LEN: usize = 32;
+
 pub(crate) fn is_ethereum(chain_name: &str) -> bool {
     ETHEREUM_CHAINS.contains(&chain_name)
 }
+
+// Detect 20-byte Ethereum-style accounts from the V14 type registry.
+// Returns `None` if the account type can't be determined.
+pub(crate) fn is_ethereum_metadata(meta: &[u8]) -> Result<Option<bool>> {
+    match account_id_len(meta)? {
+        Some(ETHEREUM_ACCOUNT_LEN) => Ok(Some(true)),
+        Some(SUBSTRATE_ACCOUNT_LEN) => Ok(Some(false)),
+        _ => Ok(None),
+    }
+}
+
+// Warn if the account type found in metadata disagrees with the chain encryption
+pub(crate) fn check_account_type(chain: &Chain, meta_values: &MetaValues) {
+    match account_type_mismatch(chain, &meta_values.meta) {
+        Ok(Some(detected)) => {
+            let origin = match chain.encryption {
+                Some(_) => "`encryption` in config",
+                None => "the list of known Ethereum chains",
+            };
+            warn!(
+                "⚠️  {} metadata has {} accounts, but {} says it's {:?}. Please set `encryption` in `config.toml`",
+                chain.name,
+                if detected { "Ethereum" } else { "Substrate" },
+                origin,
+                chain.encryption(),
+            );
+        }
+        Ok(None) => (),
+        Err(e) => warn!("Unable to detect account type of {}: {}", chain.name, e),
+    }
+}
+
+// Detected account type if it disagrees with the chain encryption
+fn account_type_mismatch(chain: &Chain, meta: &[u8]) -> Result<Option<bool>> {
+    let expected = chain.encryption() == EncryptionType::Ethereum;
+    Ok(is_ethereum_metadata(meta)?.filter(|detected| *detected != expected))
+}
+
+// Byte length of the `AccountId` used as the `System.Account` storage key
+fn account_id_len(meta: &[u8]) -> Result<Option<usize>> {
+    let metadata = match RuntimeMetadataPrefixed::decode(&mut &meta[..])?.1 {
+        RuntimeMetadata::V14(metadata) => metadata,
+        _ => return Ok(None),
+    };
+    let account_key = metadata
+        .pallets
+        .iter()
+        .find(|pallet| pallet.name == "System")
+        .and_then(|pallet| pallet.storage.as_ref())
+        .and_then(|storage| storage.entries.iter().find(|e| e.name == "Account"))
+        .and_then(|entry| match &entry.ty {
+            StorageEntryType::Map { key, .. } => Some(key.id),
+            StorageEntryType::Plain(_) => None,
+        });
+    Ok(account_key.and_then(|id| byte_len(&metadata.types, id)))
+}
+
+// Unwrap single-field wrappers down to a `[u8; N]` array
+fn byte_len(types: &PortableRegistry, id: u32) -> Option<usize> {
+    match &types.resolve(id)?.type_def {
+        TypeDef::Composite(composite) if composite.fields.len() == 1 => {
+            byte_len(types, composite.fields[0].ty.id)
+        }
+        TypeDef::Array(array) => match types.resolve(array.type_param.id)?.type_def {
+            TypeDef::Primitive(TypeDefPrimitive::U8) => Some(array.len as usize),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use frame_metadata::v14::{
+        ExtrinsicMetadata, PalletMetadata, PalletStorageMetadata, RuntimeMetadataV14,
+        StorageEntryMetadata, StorageEntryModifier, StorageHasher,
+    };
+    use parity_scale_codec::Encode;
+    use scale_info::{meta_type, TypeInfo};
+    use sp_core::crypto::AccountId32;
+
+    use super::*;
+
+    // V14 metadata with a `System.Account` map keyed by `K`
+    fn metadata<K: TypeInfo + 'static>() -> Vec<u8> {
+        let system = PalletMetadata {
+            name: "System",
+            storage: Some(PalletStorageMetadata {
+                prefix: "System",
+                entries: vec![StorageEntryMetadata {
+                    name: "Account",
+                    modifier: StorageEntryModifier::Default,
+                    ty: StorageEntryType::Map {
+                        hashers: vec![StorageHasher::Blake2_128Concat],
+                        key: meta_type::<K>(),
+                        value: meta_type::<u128>(),
+                    },
+                    default: vec![],
+                    docs: vec![],
+                }],
+            }),
+            calls: None,
+            event: None,
+            constants: vec![],
+            error: None,
+            index: 0,
+        };
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        let metadata = RuntimeMetadataV14::new(vec![system], extrinsic, meta_type::<()>());
+        RuntimeMetadataPrefixed::from(metadata).encode()
+    }
+
+    fn chain(name: &str, encryption: Option<EncryptionType>) -> Chain {
+        Chain {
+            name: name.to_string(),
+            encryption,
+            ..Chain::default()
+        }
+    }
+
+    #[test]
+    fn detect_account_id_len() {
+        assert_eq!(account_id_len(&metadata::<[u8; 20]>()).unwrap(), Some(20));
+        // `AccountId32` wraps `[u8; 32]`
+        assert_eq!(
+            account_id_len(&metadata::<AccountId32>()).unwrap(),
+            Some(32)
+        );
+        assert_eq!(account_id_len(&metadata::<u64>()).unwrap(), None);
+        assert!(account_id_len(b"not metadata").is_err());
+
+        assert_eq!(
+            is_ethereum_metadata(&metadata::<[u8; 20]>()).unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            is_ethereum_metadata(&metadata::<AccountId32>()).unwrap(),
+            Some(false)
+        );
+        assert_eq!(is_ethereum_metadata(&metadata::<[u8; 16]>()).unwrap(), None);
+    }
+
+    #[test]
+    fn account_type_agrees() {
+        let ethereum = metadata::<[u8; 20]>();
+        let substrate = metadata::<AccountId32>();
+        let moonbeam = chain("moonbeam", None);
+        assert_eq!(account_type_mismatch(&moonbeam, &ethereum).unwrap(), None);
+        let polkadot = chain("polkadot", None);
+        assert_eq!(account_type_mismatch(&polkadot, &substrate).unwrap(), None);
+        let configured = chain("new-evm", Some(EncryptionType::Ethereum));
+        assert_eq!(account_type_mismatch(&configured, &ethereum).unwrap(), None);
+    }
+
+    #[test]
+    fn account_type_mismatch_detected() {
+        let ethereum = metadata::<[u8; 20]>();
+        let substrate = metadata::<AccountId32>();
+        // A new EVM chain missing from the list and from the config
+        let new_evm = chain("new-evm", None);
+        assert_eq!(
+            account_type_mismatch(&new_evm, &ethereum).unwrap(),
+            Some(true)
+        );
+        let configured = chain("moonbeam", Some(EncryptionType::Sr25519));
+        assert_eq!(
+            account_type_mismatch(&configured, &ethereum).unwrap(),
+            Some(true)
+        );
+        let moonbeam = chain("moonbeam", None);
+        assert_eq!(
+            account_type_mismatch(&moonbeam, &substrate).unwrap(),
+            Some(false)
+        );
+    }
+}
@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::common::path::QrPath;
 use crate::common::types::ChainPortalId;
+use crate::config::EncryptionType;
 use crate::source::{read_png_source, Source};
 use crate::AppConfig;
 
@@ -43,8 +44,13 @@ pub(crate) struct ExportChainSpec {
     pub(crate) base58prefix: u16,
     pub(crate) logo: String,
     pub(crate) decimals: u8,
+    #[serde(default)]
+    pub(crate) encryption: EncryptionType,
 
     pub(crate) live_meta_version: u32,
+    /// Format version of the live metadata, e.g. 14 for V14
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) live_metadata_format: Option<u8>,
     pub(crate) metadata_qr: Option<MetadataQr>,
     pub(crate) latest_metadata: ReactAssetPath,
     pub(crate) specs_qr: QrCode,
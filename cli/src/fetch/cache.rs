@@ -0,0 +1,397 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use definitions::metadata::MetaValues;
+use definitions::network_specs::NetworkSpecs;
+use log::{info, warn};
+use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+use tempfile::NamedTempFile;
+
+use crate::config::{AppConfig, CacheMode, Chain};
+use crate::fetch::{check_chain, now, FetchedMeta, Fetcher};
+
+const SPECS_INDEX: &str = "specs.json";
+const METADATA_INDEX: &str = "metadata.json";
+
+// Points to the latest cached file of a chain
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fetched_at: u64,
+    genesis_hash: H256,
+    // Chain settings the data was fetched with, see `specs_key` and `metadata_key`
+    #[serde(default)]
+    key: String,
+    file: String,
+    #[serde(default)]
+    meta: Option<CachedMeta>,
+}
+
+// Everything from `FetchedMeta` except the metadata itself
+#[derive(Serialize, Deserialize)]
+struct CachedMeta {
+    name: String,
+    spec_version: u32,
+    block_hash: H256,
+    #[serde(default)]
+    block_number: Option<u32>,
+    base58prefix: Option<u16>,
+    warn_incomplete_extensions: bool,
+}
+
+// Fetcher wrapper that keeps fetched data on disk for `ttl` seconds
+pub(crate) struct CachedFetcher<F> {
+    inner: F,
+    dir: PathBuf,
+    ttl: u64,
+    mode: CacheMode,
+}
+
+impl<F: Fetcher> CachedFetcher<F> {
+    pub(crate) fn new(config: &AppConfig, inner: F) -> Self {
+        Self {
+            inner,
+            dir: config.cache.dir.clone(),
+            ttl: config.cache.ttl,
+            mode: config.cache.mode,
+        }
+    }
+
+    // Cached entry and its data if it can be used in the current mode.
+    // Data fetched with different chain settings is never used
+    fn read(&self, chain: &Chain, index: &str, key: &str) -> Result<Option<(CacheEntry, Vec<u8>)>> {
+        let chain_dir = self.dir.join(chain.portal_id());
+        let cached = match self.mode {
+            CacheMode::Refresh => None,
+            _ => match read_entry(&chain_dir, index) {
+                Ok(cached) => cached.filter(|(entry, _)| entry.key == key),
+                Err(e) => {
+                    warn!("Ignoring broken cache of {}: {:#}", chain.portal_id(), e);
+                    None
+                }
+            },
+        };
+        match (cached, self.mode) {
+            (Some(cached), CacheMode::Offline) => Ok(Some(cached)),
+            (Some(cached), _) if now().saturating_sub(cached.0.fetched_at) <= self.ttl => {
+                Ok(Some(cached))
+            }
+            (None, CacheMode::Offline) => bail!(
+                "{} of {} with {} is not cached. Run without --offline first",
+                index,
+                chain.portal_id(),
+                key
+            ),
+            _ => Ok(None),
+        }
+    }
+
+    fn write(&self, chain: &Chain, index: &str, entry: &CacheEntry, data: &[u8]) {
+        let chain_dir = self.dir.join(chain.portal_id());
+        if let Err(e) = write_entry(&chain_dir, index, entry, data) {
+            warn!(
+                "Failed to cache {} of {}: {:#}",
+                index,
+                chain.portal_id(),
+                e
+            );
+        }
+    }
+}
+
+impl<F: Fetcher> Fetcher for CachedFetcher<F> {
+    fn fetch_specs(&self, chain: &Chain) -> Result<NetworkSpecs> {
+        let key = specs_key(chain);
+        if let Some((_, data)) = self.read(chain, SPECS_INDEX, &key)? {
+            info!("📦 Using cached specs of {}", chain.portal_id());
+            let specs = NetworkSpecs::decode(&mut &data[..])?;
+            check_chain(chain, &specs.name, &specs.genesis_hash)?;
+            return Ok(specs);
+        }
+        let specs = self.inner.fetch_specs(chain)?;
+        let entry = CacheEntry {
+            fetched_at: now(),
+            genesis_hash: specs.genesis_hash,
+            key,
+            file: format!("specs_0x{}.scale", hex::encode(specs.genesis_hash)),
+            meta: None,
+        };
+        self.write(chain, SPECS_INDEX, &entry, &specs.encode());
+        Ok(specs)
+    }
+
+    fn fetch_metadata(&self, chain: &Chain) -> Result<FetchedMeta> {
+        let key = metadata_key(chain);
+        if let Some((entry, data)) = self.read(chain, METADATA_INDEX, &key)? {
+            if let Some(meta) = entry.meta {
+                info!("📦 Using cached metadata of {}", chain.portal_id());
+                check_chain(chain, &meta.name, &entry.genesis_hash)?;
+                return Ok(FetchedMeta {
+                    meta_values: MetaValues {
+                        name: meta.name,
+                        version: meta.spec_version,
+                        optional_base58prefix: meta.base58prefix,
+                        warn_incomplete_extensions: meta.warn_incomplete_extensions,
+                        meta: data,
+                    },
+                    genesis_hash: entry.genesis_hash,
+                    block_hash: meta.block_hash,
+                    block_number: meta.block_number,
+                });
+            }
+        }
+        let fetched = self.inner.fetch_metadata(chain)?;
+        let entry = CacheEntry {
+            fetched_at: now(),
+            genesis_hash: fetched.genesis_hash,
+            key,
+            file: format!(
+                "metadata_0x{}_{}_0x{}.scale",
+                hex::encode(fetched.genesis_hash),
+                fetched.meta_values.version,
+                hex::encode(fetched.block_hash)
+            ),
+            meta: Some(CachedMeta {
+                name: fetched.meta_values.name.clone(),
+                spec_version: fetched.meta_values.version,
+                block_hash: fetched.block_hash,
+                block_number: fetched.block_number,
+                base58prefix: fetched.meta_values.optional_base58prefix,
+                warn_incomplete_extensions: fetched.meta_values.warn_incomplete_extensions,
+            }),
+        };
+        self.write(chain, METADATA_INDEX, &entry, &fetched.meta_values.meta);
+        Ok(fetched)
+    }
+
+    fn report_failures(&self) {
+        self.inner.report_failures()
+    }
+}
+
+// Specs depend on the encryption and the token override
+fn specs_key(chain: &Chain) -> String {
+    format!(
+        "encryption {:?}, token {:?} {:?}",
+        chain.encryption(),
+        chain.token_unit,
+        chain.token_decimals
+    )
+}
+
+fn metadata_key(chain: &Chain) -> String {
+    match chain.metadata_version {
+        Some(version) => format!("metadata V{version}"),
+        None => "newest metadata".to_string(),
+    }
+}
+
+fn read_entry(dir: &Path, index: &str) -> Result<Option<(CacheEntry, Vec<u8>)>> {
+    let index_path = dir.join(index);
+    if !index_path.exists() {
+        return Ok(None);
+    }
+    let entry: CacheEntry = serde_json::from_str(&fs::read_to_string(&index_path)?)
+        .context(format!("{}", index_path.display()))?;
+    let data = fs::read(dir.join(&entry.file)).context(entry.file.clone())?;
+    Ok(Some((entry, data)))
+}
+
+// Replace the data file and then the index pointing to it.
+// Both are renamed into place, so an interrupted run or another run sharing the cache
+// never sees an index pointing to a missing or partial file
+fn write_entry(dir: &Path, index: &str, entry: &CacheEntry, data: &[u8]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let old = fs::read_to_string(dir.join(index))
+        .ok()
+        .and_then(|content| serde_json::from_str::<CacheEntry>(&content).ok());
+    write_atomic(&dir.join(&entry.file), data)?;
+    write_atomic(
+        &dir.join(index),
+        serde_json::to_string_pretty(entry)?.as_bytes(),
+    )?;
+    if let Some(old) = old {
+        if old.file != entry.file {
+            // Another run may have removed it already
+            let _ = fs::remove_file(dir.join(old.file));
+        }
+    }
+    Ok(())
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let dir = path.parent().context("cache file without a directory")?;
+    let mut file = NamedTempFile::new_in(dir).context(format!("{}", dir.display()))?;
+    file.write_all(data)?;
+    file.persist(path).context(format!("{}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use anyhow::anyhow;
+    use definitions::crypto::Encryption;
+
+    use super::*;
+    use crate::config::EncryptionType;
+
+    struct CountingFetcher {
+        calls: Cell<usize>,
+    }
+
+    impl Fetcher for CountingFetcher {
+        fn fetch_specs(&self, _chain: &Chain) -> Result<NetworkSpecs> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(NetworkSpecs {
+                base58prefix: 0,
+                color: "".to_string(),
+                decimals: 10,
+                encryption: Encryption::Sr25519,
+                genesis_hash: H256::repeat_byte(1),
+                logo: "polkadot".to_string(),
+                name: "polkadot".to_string(),
+                path_id: "//polkadot".to_string(),
+                secondary_color: "".to_string(),
+                title: "".to_string(),
+                unit: "DOT".to_string(),
+            })
+        }
+
+        fn fetch_metadata(&self, _chain: &Chain) -> Result<FetchedMeta> {
+            self.calls.set(self.calls.get() + 1);
+            Ok(FetchedMeta {
+                meta_values: MetaValues {
+                    name: "polkadot".to_string(),
+                    version: 9430,
+                    optional_base58prefix: Some(0),
+                    warn_incomplete_extensions: false,
+                    meta: vec![1, 2, 3],
+                },
+                genesis_hash: H256::repeat_byte(1),
+                block_hash: H256::repeat_byte(2),
+                block_number: Some(100),
+            })
+        }
+    }
+
+    struct OfflineFetcher;
+
+    impl Fetcher for OfflineFetcher {
+        fn fetch_specs(&self, _chain: &Chain) -> Result<NetworkSpecs> {
+            Err(anyhow!("network is not available"))
+        }
+
+        fn fetch_metadata(&self, _chain: &Chain) -> Result<FetchedMeta> {
+            Err(anyhow!("network is not available"))
+        }
+    }
+
+    fn config(dir: &Path, mode: CacheMode) -> AppConfig {
+        let mut config = AppConfig::default();
+        config.cache.dir = dir.to_path_buf();
+        config.cache.mode = mode;
+        config
+    }
+
+    #[test]
+    fn serve_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let chain = Chain::default();
+        let fetcher = CachedFetcher::new(
+            &config(dir.path(), CacheMode::Default),
+            CountingFetcher {
+                calls: Cell::new(0),
+            },
+        );
+        let specs = fetcher.fetch_specs(&chain).unwrap();
+        let meta = fetcher.fetch_metadata(&chain).unwrap();
+        assert_eq!(
+            fetcher.fetch_specs(&chain).unwrap().encode(),
+            specs.encode()
+        );
+        let cached = fetcher.fetch_metadata(&chain).unwrap();
+        assert_eq!(cached.meta_values.meta, meta.meta_values.meta);
+        assert_eq!(cached.meta_values.version, meta.meta_values.version);
+        assert_eq!(fetcher.inner.calls.get(), 2);
+
+        let offline = CachedFetcher::new(&config(dir.path(), CacheMode::Offline), OfflineFetcher);
+        assert_eq!(
+            offline.fetch_metadata(&chain).unwrap().block_hash,
+            meta.block_hash
+        );
+
+        let refresh = CachedFetcher::new(&config(dir.path(), CacheMode::Refresh), OfflineFetcher);
+        assert!(refresh.fetch_metadata(&chain).is_err());
+    }
+
+    #[test]
+    fn key_by_chain_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut chain = Chain::default();
+        let fetcher = CachedFetcher::new(
+            &config(dir.path(), CacheMode::Default),
+            CountingFetcher {
+                calls: Cell::new(0),
+            },
+        );
+        fetcher.fetch_specs(&chain).unwrap();
+        fetcher.fetch_metadata(&chain).unwrap();
+
+        chain.encryption = Some(EncryptionType::Ethereum);
+        chain.metadata_version = Some(14);
+        let offline = CachedFetcher::new(&config(dir.path(), CacheMode::Offline), OfflineFetcher);
+        assert!(offline.fetch_specs(&chain).is_err());
+        assert!(offline.fetch_metadata(&chain).is_err());
+
+        fetcher.fetch_specs(&chain).unwrap();
+        fetcher.fetch_metadata(&chain).unwrap();
+        assert_eq!(fetcher.inner.calls.get(), 4);
+        assert!(offline.fetch_specs(&chain).is_ok());
+        assert!(offline.fetch_metadata(&chain).is_ok());
+    }
+
+    #[test]
+    fn replace_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = |file: &str| CacheEntry {
+            fetched_at: 0,
+            genesis_hash: H256::zero(),
+            key: "".to_string(),
+            file: file.to_string(),
+            meta: None,
+        };
+        write_entry(dir.path(), SPECS_INDEX, &entry("a.scale"), b"a").unwrap();
+        write_entry(dir.path(), SPECS_INDEX, &entry("b.scale"), b"b").unwrap();
+
+        let (cached, data) = read_entry(dir.path(), SPECS_INDEX).unwrap().unwrap();
+        assert_eq!(
+            (cached.file.as_str(), data.as_slice()),
+            ("b.scale", &b"b"[..])
+        );
+        // Only the index and the latest data file are left, no temporary files
+        let mut files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["b.scale", SPECS_INDEX]);
+    }
+
+    #[test]
+    fn offline_without_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let fetcher = CachedFetcher::new(
+            &config(dir.path(), CacheMode::Offline),
+            CountingFetcher {
+                calls: Cell::new(0),
+            },
+        );
+        assert!(fetcher.fetch_specs(&Chain::default()).is_err());
+        assert_eq!(fetcher.inner.calls.get(), 0);
+    }
+}
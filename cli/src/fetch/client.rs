@@ -0,0 +1,140 @@
+use std::env;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use http::header::{HeaderMap, HeaderName, HeaderValue};
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::core::params::ArrayParams;
+use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::runtime::Runtime;
+
+use crate::config::{RpcEndpoint, RpcSettings};
+use crate::fetch::record::{RecordedCall, Recorder};
+
+// Source of JSON-RPC responses: a live node or a recording of it
+pub(crate) trait NodeClient {
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value>;
+
+    fn request<T: DeserializeOwned>(&self, method: &str, params: Vec<Value>) -> Result<T>
+    where
+        Self: Sized,
+    {
+        let response = self.call(method, params)?;
+        serde_json::from_value(response).context(format!("unexpected {method} response"))
+    }
+}
+
+/// Failure to reach a node or to get its response, worth retrying.
+/// Errors returned by the node itself are deterministic and never wrapped
+#[derive(Debug)]
+pub(crate) struct TransportError(pub(crate) String);
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+enum Transport {
+    Ws(WsClient),
+    Http(HttpClient),
+}
+
+// Blocking JSON-RPC client connected to a single node endpoint
+pub(crate) struct RpcClient {
+    url: String,
+    transport: Transport,
+    runtime: Runtime,
+    // Recorder and portal id of the chain responses are recorded for
+    recorder: Option<(Arc<Recorder>, String)>,
+}
+
+impl RpcClient {
+    // Scheme of the url selects the transport: ws(s) or http(s)
+    pub(crate) fn connect(endpoint: &RpcEndpoint, settings: &RpcSettings) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let headers = header_map(endpoint)?;
+        let url = endpoint.url.as_str();
+        let transport = if url.starts_with("http://") || url.starts_with("https://") {
+            let client = runtime.block_on(async {
+                HttpClientBuilder::default()
+                    .request_timeout(settings.request_timeout())
+                    .set_headers(headers)
+                    .build(url)
+            });
+            Transport::Http(client.map_err(connect_error)?)
+        } else {
+            let client = runtime.block_on(
+                WsClientBuilder::default()
+                    .connection_timeout(settings.connect_timeout())
+                    .request_timeout(settings.request_timeout())
+                    .set_headers(headers)
+                    .build(url),
+            );
+            Transport::Ws(client.map_err(connect_error)?)
+        };
+        Ok(Self {
+            url: url.to_string(),
+            transport,
+            runtime,
+            recorder: None,
+        })
+    }
+
+    // Record every response of this client
+    pub(crate) fn record(mut self, recorder: Arc<Recorder>, portal_id: String) -> Self {
+        self.recorder = Some((recorder, portal_id));
+        self
+    }
+}
+
+impl NodeClient for RpcClient {
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value> {
+        let mut array = ArrayParams::new();
+        for param in &params {
+            array.insert(param)?;
+        }
+        let response: Result<Value, _> = match &self.transport {
+            Transport::Ws(client) => self.runtime.block_on(client.request(method, array)),
+            Transport::Http(client) => self.runtime.block_on(client.request(method, array)),
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(e @ jsonrpsee::core::Error::Call(_)) => {
+                return Err(anyhow!(e).context(format!("{method} failed")))
+            }
+            Err(e) => return Err(anyhow!(TransportError(format!("{method} failed: {e}")))),
+        };
+        if let Some((recorder, portal_id)) = &self.recorder {
+            let call = RecordedCall::new(&self.url, method, params, response.clone());
+            recorder.record(portal_id, call)?;
+        }
+        Ok(response)
+    }
+}
+
+fn connect_error(e: jsonrpsee::core::Error) -> anyhow::Error {
+    anyhow!(TransportError(format!("failed to connect: {e}")))
+}
+
+// Header values are marked sensitive and never make it into error messages
+fn header_map(endpoint: &RpcEndpoint) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    for (name, value) in endpoint.resolve_headers(|var| env::var(var).ok())? {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .context(format!("invalid header name `{name}`"))?;
+        let mut header_value = HeaderValue::from_str(&value)
+            .map_err(|_| anyhow::anyhow!("invalid value of `{}` header", name))?;
+        header_value.set_sensitive(true);
+        headers.insert(header_name, header_value);
+    }
+    Ok(headers)
+}
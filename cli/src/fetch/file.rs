@@ -0,0 +1,242 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use definitions::metadata::MetaValues;
+use definitions::network_specs::NetworkSpecs;
+use log::info;
+use parity_scale_codec::Decode;
+use serde::Deserialize;
+use sp_core::H256;
+
+use crate::config::Chain;
+use crate::fetch::{check_chain, FetchedMeta, Fetcher};
+
+const SPECS_FILE: &str = "specs.json";
+// `state_getMetadata` output
+const METADATA_HEX_FILE: &str = "metadata.hex";
+const METADATA_SCALE_FILE: &str = "metadata.scale";
+
+// Network specs and the block the metadata was taken at
+#[derive(Deserialize)]
+struct SpecsFile {
+    genesis_hash: H256,
+    block_hash: H256,
+    #[serde(default)]
+    block_number: Option<u32>,
+    // Hex of SCALE encoded network specs
+    specs: String,
+}
+
+// Reads specs and metadata prepared in `<dir>/<portal_id>/` instead of querying RPC
+pub(crate) struct FileFetcher {
+    dir: PathBuf,
+}
+
+impl FileFetcher {
+    pub(crate) fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn chain_dir(&self, chain: &Chain) -> PathBuf {
+        self.dir.join(chain.portal_id())
+    }
+
+    fn read_specs(&self, chain: &Chain) -> Result<SpecsFile> {
+        let chain_dir = self.chain_dir(chain);
+        info!(
+            "📂 Reading {} from {}",
+            chain.portal_id(),
+            chain_dir.display()
+        );
+        let specs_path = chain_dir.join(SPECS_FILE);
+        serde_json::from_str(
+            &fs::read_to_string(&specs_path).context(format!("{}", specs_path.display()))?,
+        )
+        .context(format!("{}", specs_path.display()))
+    }
+}
+
+impl Fetcher for FileFetcher {
+    fn fetch_specs(&self, chain: &Chain) -> Result<NetworkSpecs> {
+        let specs_file = self.read_specs(chain)?;
+        let specs = hex::decode(specs_file.specs.trim_start_matches("0x"))?;
+        let specs = NetworkSpecs::decode(&mut &specs[..]).context("invalid specs")?;
+        check_chain(chain, &specs.name, &specs.genesis_hash)?;
+        Ok(specs)
+    }
+
+    fn fetch_metadata(&self, chain: &Chain) -> Result<FetchedMeta> {
+        let specs_file = self.read_specs(chain)?;
+        let meta = read_metadata(&self.chain_dir(chain))?;
+        let meta_values = MetaValues::from_slice_metadata(&meta).map_err(|e| anyhow!("{:?}", e))?;
+        check_chain(chain, &meta_values.name, &specs_file.genesis_hash)?;
+        Ok(FetchedMeta {
+            meta_values,
+            genesis_hash: specs_file.genesis_hash,
+            block_hash: specs_file.block_hash,
+            block_number: specs_file.block_number,
+        })
+    }
+}
+
+// Raw SCALE metadata or its hex, optionally as a quoted JSON string
+fn read_metadata(chain_dir: &Path) -> Result<Vec<u8>> {
+    let scale_path = chain_dir.join(METADATA_SCALE_FILE);
+    if scale_path.exists() {
+        return Ok(fs::read(scale_path)?);
+    }
+    let hex_path = chain_dir.join(METADATA_HEX_FILE);
+    if !hex_path.exists() {
+        bail!(
+            "neither {} nor {} found in {}",
+            METADATA_HEX_FILE,
+            METADATA_SCALE_FILE,
+            chain_dir.display()
+        );
+    }
+    let content = fs::read_to_string(&hex_path)?;
+    let hex = content.trim().trim_matches('"').trim_start_matches("0x");
+    hex::decode(hex).context(format!("{}", hex_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use definitions::crypto::Encryption;
+    use frame_metadata::v14::{
+        ExtrinsicMetadata, PalletConstantMetadata, PalletMetadata, RuntimeMetadataV14,
+    };
+    use frame_metadata::RuntimeMetadataPrefixed;
+    use parity_scale_codec::Encode;
+    use scale_info::meta_type;
+    use serde_json::json;
+
+    use super::*;
+
+    const GENESIS_HASH: &str = "0x91b171bb158e2d3848fa23a9f1c25182fb8e20313b2c1eb49219da7a70ce90c3";
+
+    fn specs(name: &str) -> NetworkSpecs {
+        NetworkSpecs {
+            base58prefix: 0,
+            color: "".to_string(),
+            decimals: 10,
+            encryption: Encryption::Sr25519,
+            genesis_hash: H256::from_str(GENESIS_HASH.trim_start_matches("0x")).unwrap(),
+            logo: name.to_string(),
+            name: name.to_string(),
+            path_id: format!("//{name}"),
+            secondary_color: "".to_string(),
+            title: "".to_string(),
+            unit: "DOT".to_string(),
+        }
+    }
+
+    // V14 metadata with only the runtime version of `spec_name`
+    fn metadata(spec_name: &str) -> Vec<u8> {
+        // `RuntimeVersion` without apis, so without `state_version`
+        let version = (
+            spec_name,
+            spec_name,
+            0u32,
+            9430u32,
+            0u32,
+            Vec::<([u8; 8], u32)>::new(),
+            24u32,
+        );
+        let system = PalletMetadata {
+            name: "System",
+            storage: None,
+            calls: None,
+            event: None,
+            constants: vec![PalletConstantMetadata {
+                name: "Version",
+                ty: meta_type::<()>(),
+                value: version.encode(),
+                docs: vec![],
+            }],
+            error: None,
+            index: 0,
+        };
+        let extrinsic = ExtrinsicMetadata {
+            ty: meta_type::<()>(),
+            version: 4,
+            signed_extensions: vec![],
+        };
+        let metadata = RuntimeMetadataV14::new(vec![system], extrinsic, meta_type::<()>());
+        RuntimeMetadataPrefixed::from(metadata).encode()
+    }
+
+    // Prepare `<dir>/polkadot/` with specs of `name` and metadata of `spec_name`
+    fn write_chain(dir: &Path, name: &str, spec_name: &str) {
+        let chain_dir = dir.join("polkadot");
+        fs::create_dir_all(&chain_dir).unwrap();
+        let specs_file = json!({
+            "genesis_hash": GENESIS_HASH,
+            "block_hash": format!("0x{}", "11".repeat(32)),
+            "specs": format!("0x{}", hex::encode(specs(name).encode())),
+        });
+        fs::write(chain_dir.join(SPECS_FILE), specs_file.to_string()).unwrap();
+        fs::write(chain_dir.join(METADATA_SCALE_FILE), metadata(spec_name)).unwrap();
+    }
+
+    fn chain(genesis_hash: &str) -> Chain {
+        Chain {
+            genesis_hash: Some(genesis_hash.to_string()),
+            ..Chain::default()
+        }
+    }
+
+    #[test]
+    fn read_matching_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        write_chain(dir.path(), "Polkadot", "polkadot");
+        let fetcher = FileFetcher::new(dir.path());
+
+        let specs = fetcher.fetch_specs(&chain(GENESIS_HASH)).unwrap();
+        assert_eq!(specs.name, "Polkadot");
+        let meta = fetcher.fetch_metadata(&chain(GENESIS_HASH)).unwrap();
+        assert_eq!(meta.meta_values.version, 9430);
+        assert_eq!(meta.block_hash, H256::repeat_byte(0x11));
+    }
+
+    #[test]
+    fn reject_name_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        write_chain(dir.path(), "kusama", "kusama");
+        let fetcher = FileFetcher::new(dir.path());
+
+        let e = fetcher.fetch_specs(&chain(GENESIS_HASH)).unwrap_err();
+        assert!(e.to_string().contains("name mismatch"), "{e}");
+        let e = fetcher.fetch_metadata(&chain(GENESIS_HASH)).unwrap_err();
+        assert!(e.to_string().contains("name mismatch"), "{e}");
+    }
+
+    #[test]
+    fn reject_genesis_hash_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        write_chain(dir.path(), "polkadot", "polkadot");
+        let fetcher = FileFetcher::new(dir.path());
+        let other = format!("0x{}", "22".repeat(32));
+
+        let e = fetcher.fetch_specs(&chain(&other)).unwrap_err();
+        assert!(e.to_string().contains("Genesis hash mismatch"), "{e}");
+        let e = fetcher.fetch_metadata(&chain(&other)).unwrap_err();
+        assert!(e.to_string().contains("Genesis hash mismatch"), "{e}");
+    }
+
+    #[test]
+    fn read_hex_or_scale_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_metadata(dir.path()).is_err());
+
+        fs::write(dir.path().join(METADATA_HEX_FILE), "\"0x6d657461\"\n").unwrap();
+        assert_eq!(read_metadata(dir.path()).unwrap(), b"meta");
+
+        fs::write(dir.path().join(METADATA_SCALE_FILE), b"atem").unwrap();
+        assert_eq!(read_metadata(dir.path()).unwrap(), b"atem");
+    }
+}
@@ -0,0 +1,213 @@
+mod cache;
+mod client;
+mod file;
+mod node;
+mod record;
+mod rpc;
+mod specs;
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use definitions::metadata::MetaValues;
+use definitions::network_specs::NetworkSpecs;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sp_core::H256;
+
+use crate::common::logger::{capture, log_captured, CapturedRecord};
+use crate::config::{AppConfig, Chain, Recording};
+use crate::export::{ExportData, ReactAssetPath};
+
+pub(crate) use crate::fetch::cache::CachedFetcher;
+pub(crate) use crate::fetch::client::RpcClient;
+pub(crate) use crate::fetch::file::FileFetcher;
+pub(crate) use crate::fetch::node::{
+    fetch_meta, fetch_properties, metadata_format, BlockId, PARSABLE_METADATA_VERSIONS,
+};
+pub(crate) use crate::fetch::record::ReplayFetcher;
+pub(crate) use crate::fetch::rpc::RpcFetcher;
+pub(crate) use crate::fetch::specs::build_specs;
+
+/// Metadata fetched from a node together with the block it was taken at
+pub(crate) struct FetchedMeta {
+    pub(crate) meta_values: MetaValues,
+    pub(crate) genesis_hash: H256,
+    pub(crate) block_hash: H256,
+    /// Unknown for data from files and old caches
+    pub(crate) block_number: Option<u32>,
+}
+
+pub(crate) trait Fetcher {
+    fn fetch_specs(&self, chain: &Chain) -> Result<NetworkSpecs>;
+    fn fetch_metadata(&self, chain: &Chain) -> Result<FetchedMeta>;
+
+    // Log failures worth a summary at the end of the command
+    fn report_failures(&self) {}
+}
+
+impl<F: Fetcher + ?Sized> Fetcher for Box<F> {
+    fn fetch_specs(&self, chain: &Chain) -> Result<NetworkSpecs> {
+        (**self).fetch_specs(chain)
+    }
+
+    fn fetch_metadata(&self, chain: &Chain) -> Result<FetchedMeta> {
+        (**self).fetch_metadata(chain)
+    }
+
+    fn report_failures(&self) {
+        (**self).report_failures()
+    }
+}
+
+// Fetcher for commands that query nodes.
+// Recorded runs bypass the cache, so that every response ends up in the recording.
+// Data at an explicit block is never cached
+pub(crate) fn node_fetcher(config: &AppConfig) -> Box<dyn Fetcher + Sync> {
+    match &config.recording {
+        Some(Recording::Replay(dir)) => Box::new(ReplayFetcher::new(dir, config.at)),
+        Some(Recording::Record(_)) => Box::new(RpcFetcher::new(config)),
+        None if config.at.is_some() => Box::new(RpcFetcher::new(config)),
+        None => Box::new(CachedFetcher::new(config, RpcFetcher::new(config))),
+    }
+}
+
+// Run `f` for every chain using up to `jobs` threads.
+// Results are returned in the order of `chains`. Log records of every chain
+// are held back and logged together, in the order of `chains` too
+pub(crate) fn fetch_parallel<T, F>(chains: &[&Chain], jobs: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(&Chain) -> T + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new(chains.iter().map(|_| None).collect());
+    let logs = Mutex::new(LogQueue {
+        pending: chains.iter().map(|_| None).collect(),
+        logged: 0,
+    });
+    thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, chains.len().max(1)) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= chains.len() {
+                    break;
+                }
+                let (result, records) = capture(|| f(chains[i]));
+                results.lock().unwrap()[i] = Some(result);
+                logs.lock().unwrap().push(i, records);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every chain is processed"))
+        .collect()
+}
+
+// Log records of chains waiting for the chains before them to finish
+struct LogQueue {
+    pending: Vec<Option<Vec<CapturedRecord>>>,
+    logged: usize,
+}
+
+impl LogQueue {
+    fn push(&mut self, i: usize, records: Vec<CapturedRecord>) {
+        self.pending[i] = Some(records);
+        while let Some(records) = self.pending.get_mut(self.logged).and_then(Option::take) {
+            log_captured(records);
+            self.logged += 1;
+        }
+    }
+}
+
+// Make sure the fetched data belongs to the configured chain
+fn check_chain(chain: &Chain, name: &str, genesis_hash: &H256) -> Result<()> {
+    if name.to_lowercase() != chain.name {
+        bail!(
+            "Network name mismatch. Expected {}, got {}. Please fix it in `config.toml`",
+            chain.name,
+            name
+        )
+    }
+    if let Some(expected) = chain.genesis_hash() {
+        if &expected != genesis_hash {
+            bail!(
+                "Genesis hash mismatch for {}. Expected 0x{}, got 0x{}. The node serves a different network",
+                chain.portal_id(),
+                hex::encode(expected),
+                hex::encode(genesis_hash)
+            )
+        }
+    }
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[derive(Serialize, Deserialize)]
+struct PkgJson {
+    homepage: String,
+}
+pub(crate) fn fetch_deployed_data(config: &AppConfig) -> Result<ExportData> {
+    let pkg_json = fs::read_to_string(Path::new("package.json"))?;
+    let pkg_json: PkgJson = serde_json::from_str(&pkg_json)?;
+
+    let data_file = ReactAssetPath::from_fs_path(&config.data_file, &config.public_dir)?;
+    let url = Url::parse(&pkg_json.homepage)?;
+    let url = url.join(&data_file.to_string())?;
+
+    Ok(reqwest::blocking::get(url)?.json::<ExportData>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn fetch_parallel_in_order() {
+        let chains: Vec<Chain> = (0..10)
+            .map(|i| Chain {
+                name: format!("chain{i}"),
+                ..Chain::default()
+            })
+            .collect();
+        let selected: Vec<&Chain> = chains.iter().collect();
+        let results = fetch_parallel(&selected, 4, |chain| {
+            let i: u64 = chain.name.trim_start_matches("chain").parse().unwrap();
+            // Later chains finish first
+            thread::sleep(Duration::from_millis(5 * (10 - i)));
+            match i % 3 {
+                0 => Err(anyhow!("{} failed", chain.name)),
+                _ => Ok(chain.name.clone()),
+            }
+        });
+
+        assert_eq!(results.len(), chains.len());
+        for (i, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(name) => assert_eq!(name, format!("chain{i}")),
+                Err(e) => {
+                    assert_eq!(i % 3, 0);
+                    assert_eq!(e.to_string(), format!("chain{i} failed"));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use definitions::metadata::MetaValues;
+use definitions::network_specs::NetworkSpecs;
+use log::{debug, info};
+use parity_scale_codec::{Decode, Encode};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use sp_core::H256;
+
+use crate::config::Chain;
+use crate::fetch::client::NodeClient;
+use crate::fetch::specs::build_specs;
+use crate::fetch::FetchedMeta;
+
+// Metadata versions the `definitions` parser can handle
+pub(crate) const PARSABLE_METADATA_VERSIONS: [u32; 3] = [12, 13, 14];
+
+/// Block to fetch data at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BlockId {
+    Hash(H256),
+    Number(u32),
+    /// The first block where the runtime had this spec version
+    SpecVersion(u32),
+}
+
+impl FromStr for BlockId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("0x") {
+            Some(hash) => Ok(Self::Hash(
+                H256::from_str(hash).context("invalid block hash")?,
+            )),
+            None => Ok(Self::Number(s.parse().context("invalid block number")?)),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Header {
+    // Hex encoded
+    number: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuntimeVersion {
+    spec_version: u32,
+}
+
+// Hash and number of the requested block or of the finalized head
+pub(crate) fn resolve_block<C: NodeClient>(client: &C, at: Option<BlockId>) -> Result<(H256, u32)> {
+    let hash: H256 = match at {
+        None => client.request("chain_getFinalizedHead", vec![])?,
+        Some(BlockId::Hash(hash)) => hash,
+        Some(BlockId::Number(number)) => block_hash(client, number)?,
+        Some(BlockId::SpecVersion(spec_version)) => {
+            let (first, last) = find_spec_version(client, spec_version)?;
+            info!(
+                "🕰  Spec version {} was live in blocks #{}..=#{}",
+                spec_version, first, last
+            );
+            block_hash(client, first)?
+        }
+    };
+    let header: Option<Header> = client.request("chain_getHeader", vec![json!(hash)])?;
+    let number = match header {
+        Some(header) => u32::from_str_radix(header.number.trim_start_matches("0x"), 16)
+            .context(format!("invalid block number {}", header.number))?,
+        None => bail!("block 0x{} not found", hex::encode(hash)),
+    };
+    if let Some(BlockId::Number(expected)) = at {
+        if number != expected {
+            bail!("node returned block #{} instead of #{}", number, expected);
+        }
+    }
+    Ok((hash, number))
+}
+
+fn block_hash<C: NodeClient>(client: &C, number: u32) -> Result<H256> {
+    client
+        .request::<Option<H256>>("chain_getBlockHash", vec![json!(number)])?
+        .ok_or_else(|| anyhow!("block #{} not found", number))
+}
+
+// Range of finalized blocks where the runtime had the given spec version.
+// Spec versions never decrease, so the range is found by binary search over block numbers
+pub(crate) fn find_spec_version<C: NodeClient>(
+    client: &C,
+    spec_version: u32,
+) -> Result<(u32, u32)> {
+    let (_, head) = resolve_block(client, None)?;
+    let version_at = |number: u32| -> Result<u32> {
+        let hash = block_hash(client, number)?;
+        let version: RuntimeVersion =
+            client.request("state_getRuntimeVersion", vec![json!(hash)])?;
+        debug!(
+            "Block #{} has spec version {}",
+            number, version.spec_version
+        );
+        Ok(version.spec_version)
+    };
+
+    let first = partition_point(0, head, |n| Ok(version_at(n)? < spec_version))?;
+    if first > head || version_at(first)? != spec_version {
+        bail!(
+            "spec version {} was not live in blocks #0..=#{}",
+            spec_version,
+            head
+        );
+    }
+    let end = partition_point(first, head, |n| Ok(version_at(n)? <= spec_version))?;
+    Ok((first, end - 1))
+}
+
+// The first number in `low..=high` for which `pred` is false, or `high + 1` if there is none.
+// `pred` must be true for a prefix of the range and false for the rest
+fn partition_point<F>(low: u32, high: u32, mut pred: F) -> Result<u32>
+where
+    F: FnMut(u32) -> Result<bool>,
+{
+    let (mut low, mut high) = (low as u64, high as u64 + 1);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid as u32)? {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low as u32)
+}
+
+// Fetch metadata at the given block or at the finalized head.
+// `version` overrides the newest parsable metadata version
+pub(crate) fn fetch_meta<C: NodeClient>(
+    client: &C,
+    at: Option<BlockId>,
+    version: Option<u32>,
+) -> Result<FetchedMeta> {
+    let (block_hash, block_number) = resolve_block(client, at)?;
+    let genesis_hash: H256 = client.request("chain_getBlockHash", vec![json!(0)])?;
+    let meta = fetch_metadata_bytes(client, block_hash, version)?;
+    if let Some(version) = version {
+        ensure!(
+            metadata_format(&meta) == Some(version as u8),
+            "node returned metadata V{:?} instead of V{}",
+            metadata_format(&meta),
+            version
+        );
+    }
+
+    let meta_values = MetaValues::from_slice_metadata(&meta).map_err(|e| anyhow!("{:?}", e))?;
+    Ok(FetchedMeta {
+        meta_values,
+        genesis_hash,
+        block_hash,
+        block_number: Some(block_number),
+    })
+}
+
+// Runtimes without the `Metadata_metadata_versions` API only provide `state_getMetadata`
+fn fetch_metadata_bytes<C: NodeClient>(
+    client: &C,
+    block_hash: H256,
+    version: Option<u32>,
+) -> Result<Vec<u8>> {
+    let versions = runtime_call(client, "Metadata_metadata_versions", &[], block_hash)
+        .and_then(|output| Ok(Vec::<u32>::decode(&mut &output[..])?));
+    let versions = match versions {
+        Ok(versions) => versions,
+        Err(e) => {
+            debug!("Falling back to state_getMetadata: {:#}", e);
+            let meta: String = client.request("state_getMetadata", vec![json!(block_hash)])?;
+            return Ok(hex::decode(meta.trim_start_matches("0x"))?);
+        }
+    };
+    let version = select_metadata_version(&versions, version)?;
+    debug!("Fetching metadata V{}, available: {:?}", version, versions);
+    let output = runtime_call(
+        client,
+        "Metadata_metadata_at_version",
+        &version.encode(),
+        block_hash,
+    )?;
+    match Option::<Vec<u8>>::decode(&mut &output[..])? {
+        Some(meta) => Ok(meta),
+        None => bail!("runtime returned no metadata V{}", version),
+    }
+}
+
+// The requested version or the newest one `definitions` can parse
+fn select_metadata_version(available: &[u32], requested: Option<u32>) -> Result<u32> {
+    match requested {
+        Some(version) if available.contains(&version) => Ok(version),
+        Some(version) => bail!(
+            "metadata V{} is not provided by the runtime, available: {:?}",
+            version,
+            available
+        ),
+        None => available
+            .iter()
+            .filter(|v| PARSABLE_METADATA_VERSIONS.contains(v))
+            .max()
+            .copied()
+            .ok_or_else(|| {
+                anyhow!(
+                    "none of the metadata versions {:?} provided by the runtime can be parsed",
+                    available
+                )
+            }),
+    }
+}
+
+// Call a runtime API at the given block, input and output are SCALE encoded
+fn runtime_call<C: NodeClient>(
+    client: &C,
+    method: &str,
+    input: &[u8],
+    block_hash: H256,
+) -> Result<Vec<u8>> {
+    let output: String = client.request(
+        "state_call",
+        vec![
+            json!(method),
+            json!(format!("0x{}", hex::encode(input))),
+            json!(block_hash),
+        ],
+    )?;
+    Ok(hex::decode(output.trim_start_matches("0x"))?)
+}
+
+// Format version of `meta` prefixed metadata
+pub(crate) fn metadata_format(meta: &[u8]) -> Option<u8> {
+    match meta.strip_prefix(b"meta") {
+        Some([version, ..]) => Some(*version),
+        _ => None,
+    }
+}
+
+pub(crate) fn fetch_properties<C: NodeClient>(client: &C) -> Result<Map<String, Value>> {
+    client.request("system_properties", vec![])
+}
+
+// Specs are built from metadata and properties of our own client, so that
+// endpoints with headers and http(s) endpoints can serve them too
+pub(crate) fn fetch_network_specs<C: NodeClient>(
+    client: &C,
+    chain: &Chain,
+    at: Option<BlockId>,
+) -> Result<NetworkSpecs> {
+    let meta = fetch_meta(client, at, chain.metadata_version)?;
+    let properties = fetch_properties(client)?;
+    build_specs(chain, &meta.meta_values, meta.genesis_hash, &properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use super::*;
+    use crate::config::{RpcEndpoint, RpcSettings};
+    use crate::fetch::client::{RpcClient, TransportError};
+
+    #[test]
+    fn parse_block_id() {
+        assert_eq!(BlockId::from_str("42").unwrap(), BlockId::Number(42));
+        let hash = format!("0x{}", "ab".repeat(32));
+        assert_eq!(
+            BlockId::from_str(&hash).unwrap(),
+            BlockId::Hash(H256::repeat_byte(0xab))
+        );
+        assert!(BlockId::from_str("0x12").is_err());
+        assert!(BlockId::from_str("latest").is_err());
+    }
+
+    // Serves a chain of `versions.len()` blocks, block `n` has hash `n + 1`
+    struct MockNode {
+        versions: Vec<u32>,
+    }
+
+    impl MockNode {
+        fn number(params: &[Value]) -> usize {
+            let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+            hash.to_low_u64_be() as usize - 1
+        }
+    }
+
+    impl NodeClient for MockNode {
+        fn call(&self, method: &str, params: Vec<Value>) -> Result<Value> {
+            let head = self.versions.len() - 1;
+            Ok(match method {
+                "chain_getFinalizedHead" => json!(H256::from_low_u64_be(head as u64 + 1)),
+                "chain_getBlockHash" => match params[0].as_u64() {
+                    Some(n) if n as usize <= head => json!(H256::from_low_u64_be(n + 1)),
+                    _ => Value::Null,
+                },
+                "chain_getHeader" => json!({ "number": format!("0x{:x}", Self::number(&params)) }),
+                "state_getRuntimeVersion" => {
+                    json!({ "specVersion": self.versions[Self::number(&params)] })
+                }
+                "system_properties" => json!({ "ss58Format": 0, "tokenSymbol": "DOT" }),
+                _ => bail!("unexpected call {}", method),
+            })
+        }
+    }
+
+    #[test]
+    fn find_historical_spec_version() {
+        let node = MockNode {
+            versions: vec![1, 1, 1, 2, 2, 3, 3, 3, 3],
+        };
+        assert_eq!(find_spec_version(&node, 1).unwrap(), (0, 2));
+        assert_eq!(find_spec_version(&node, 2).unwrap(), (3, 4));
+        assert_eq!(find_spec_version(&node, 3).unwrap(), (5, 8));
+        assert!(find_spec_version(&node, 0).is_err());
+        assert!(find_spec_version(&node, 4).is_err());
+
+        let (hash, number) = resolve_block(&node, Some(BlockId::SpecVersion(2))).unwrap();
+        assert_eq!((hash, number), (H256::from_low_u64_be(4), 3));
+    }
+
+    #[test]
+    fn select_newest_parsable_metadata() {
+        assert_eq!(select_metadata_version(&[14, 15], None).unwrap(), 14);
+        assert_eq!(select_metadata_version(&[14, 15], Some(15)).unwrap(), 15);
+        assert!(select_metadata_version(&[14, 15], Some(16)).is_err());
+        assert!(select_metadata_version(&[15, 16], None).is_err());
+        assert_eq!(metadata_format(b"meta\x0e\x00"), Some(14));
+        assert_eq!(metadata_format(b"\x0e"), None);
+    }
+
+    #[test]
+    fn skipped_spec_version() {
+        let node = MockNode {
+            versions: vec![1, 1, 3, 3],
+        };
+        assert!(find_spec_version(&node, 2).is_err());
+    }
+
+    // Minimal HTTP JSON-RPC server answering with `node`, returns its url.
+    // Requests without `authorization` header, if set, are rejected with 401
+    fn serve(node: MockNode, authorization: Option<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let _ = handle_connection(&node, authorization, stream.unwrap());
+            }
+        });
+        url
+    }
+
+    fn handle_connection(
+        node: &MockNode,
+        authorization: Option<&str>,
+        stream: TcpStream,
+    ) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        loop {
+            let mut content_length = 0;
+            let mut authorized = authorization.is_none();
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line)? == 0 {
+                    return Ok(());
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                    if name.eq_ignore_ascii_case("authorization") {
+                        authorized = authorization == Some(value.trim());
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            if !authorized {
+                write!(
+                    writer,
+                    "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n"
+                )?;
+                continue;
+            }
+
+            let request: Value = serde_json::from_slice(&body).unwrap();
+            let params = request["params"].as_array().cloned().unwrap_or_default();
+            let response = match node.call(request["method"].as_str().unwrap(), params) {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }),
+                Err(e) => json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": -32601, "message": e.to_string() },
+                }),
+            }
+            .to_string();
+            write!(
+                writer,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                response.len(),
+                response
+            )?;
+        }
+    }
+
+    fn connect(url: &str) -> RpcClient {
+        RpcClient::connect(&RpcEndpoint::from(url), &RpcSettings::default()).unwrap()
+    }
+
+    #[test]
+    fn find_spec_version_over_rpc() {
+        let client = connect(&serve(
+            MockNode {
+                versions: vec![1, 1, 2, 2, 2, 3],
+            },
+            None,
+        ));
+        assert_eq!(find_spec_version(&client, 2).unwrap(), (2, 4));
+        assert!(find_spec_version(&client, 4).is_err());
+
+        let (hash, number) = resolve_block(&client, Some(BlockId::SpecVersion(3))).unwrap();
+        assert_eq!((hash, number), (H256::from_low_u64_be(6), 5));
+        let (_, number) = resolve_block(&client, Some(BlockId::Number(1))).unwrap();
+        assert_eq!(number, 1);
+        assert!(resolve_block(&client, Some(BlockId::Number(6))).is_err());
+    }
+
+    #[test]
+    fn rpc_errors() {
+        let client = connect(&serve(MockNode { versions: vec![1] }, None));
+        // Errors returned by the node are the same on every attempt
+        let e = client.call("state_getMetadata", vec![]).unwrap_err();
+        assert!(!e.is::<TransportError>());
+
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let e = connect(&url)
+            .call("chain_getFinalizedHead", vec![])
+            .unwrap_err();
+        assert!(e.is::<TransportError>());
+    }
+
+    #[test]
+    fn send_endpoint_headers() {
+        let url = serve(MockNode { versions: vec![1] }, Some("Bearer secret"));
+        assert!(fetch_properties(&connect(&url)).is_err());
+
+        let endpoint = RpcEndpoint {
+            url,
+            headers: [("Authorization".to_string(), "Bearer secret".to_string())].into(),
+        };
+        let client = RpcClient::connect(&endpoint, &RpcSettings::default()).unwrap();
+        let properties = fetch_properties(&client).unwrap();
+        assert_eq!(properties["tokenSymbol"], "DOT");
+    }
+}
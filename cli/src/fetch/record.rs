@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+use definitions::network_specs::NetworkSpecs;
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sp_core::H256;
+
+use crate::config::Chain;
+use crate::fetch::client::NodeClient;
+use crate::fetch::node::{fetch_meta, fetch_network_specs, BlockId};
+use crate::fetch::{check_chain, now, FetchedMeta, Fetcher};
+
+/// A single JSON-RPC response used during the run
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub(crate) struct RecordedCall {
+    pub(crate) endpoint: String,
+    pub(crate) method: String,
+    pub(crate) params: Vec<Value>,
+    /// Block the response refers to, if known
+    pub(crate) block_hash: Option<H256>,
+    /// Unix time of the response
+    pub(crate) timestamp: u64,
+    pub(crate) response: Value,
+}
+
+impl RecordedCall {
+    pub(crate) fn new(endpoint: &str, method: &str, params: Vec<Value>, response: Value) -> Self {
+        // The block is either passed as a parameter or returned by `chain_getBlockHash`
+        let block_hash = params
+            .iter()
+            .chain((method == "chain_getBlockHash").then_some(&response))
+            .find_map(|v| serde_json::from_value::<H256>(v.clone()).ok());
+        Self {
+            endpoint: endpoint.to_string(),
+            method: method.to_string(),
+            params,
+            block_hash,
+            timestamp: now(),
+            response,
+        }
+    }
+}
+
+// Saves responses to `<dir>/<portal_id>.json` as they arrive
+pub(crate) struct Recorder {
+    dir: PathBuf,
+    calls: Mutex<HashMap<String, Vec<RecordedCall>>>,
+}
+
+impl Recorder {
+    pub(crate) fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn record(&self, portal_id: &str, call: RecordedCall) -> Result<()> {
+        let mut calls = self.calls.lock().unwrap();
+        let chain_calls = calls.entry(portal_id.to_string()).or_default();
+        chain_calls.push(call);
+
+        fs::create_dir_all(&self.dir)?;
+        let path = recording_path(&self.dir, portal_id);
+        fs::write(&path, serde_json::to_string_pretty(chain_calls)?)
+            .context(format!("{}", path.display()))?;
+        Ok(())
+    }
+}
+
+// Serves the last recorded response to a method with the same params.
+// Earlier responses belong to attempts that were retried or to other endpoints,
+// so the order and number of calls don't have to match the recorded run
+struct ReplayClient {
+    responses: HashMap<(String, String), Value>,
+}
+
+impl ReplayClient {
+    fn new(calls: Vec<RecordedCall>) -> Self {
+        let responses = calls
+            .into_iter()
+            .map(|c| (call_key(&c.method, &c.params), c.response))
+            .collect();
+        Self { responses }
+    }
+}
+
+impl NodeClient for ReplayClient {
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value> {
+        match self.responses.get(&call_key(method, &params)) {
+            Some(response) => Ok(response.clone()),
+            None => bail!("no recorded response to {} {:?}", method, params),
+        }
+    }
+}
+
+fn call_key(method: &str, params: &[Value]) -> (String, String) {
+    (method.to_string(), Value::from(params).to_string())
+}
+
+// Fetcher that replays a run recorded with `--record`
+pub(crate) struct ReplayFetcher {
+    dir: PathBuf,
+    // Must match `--at` of the recorded run
+    at: Option<BlockId>,
+    clients: Mutex<HashMap<String, Arc<ReplayClient>>>,
+}
+
+impl ReplayFetcher {
+    pub(crate) fn new(dir: impl AsRef<Path>, at: Option<BlockId>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            at,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Specs and metadata of a chain are replayed from the same recording
+    fn client(&self, chain: &Chain) -> Result<Arc<ReplayClient>> {
+        let portal_id = chain.portal_id();
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&portal_id) {
+            return Ok(client.clone());
+        }
+        let path = recording_path(&self.dir, &portal_id);
+        info!("📼 Replaying {} from {}", portal_id, path.display());
+        let calls: Vec<RecordedCall> = serde_json::from_str(
+            &fs::read_to_string(&path).context(format!("{}", path.display()))?,
+        )?;
+        let client = Arc::new(ReplayClient::new(calls));
+        clients.insert(portal_id, client.clone());
+        Ok(client)
+    }
+}
+
+impl Fetcher for ReplayFetcher {
+    fn fetch_specs(&self, chain: &Chain) -> Result<NetworkSpecs> {
+        let specs = fetch_network_specs(self.client(chain)?.as_ref(), chain, self.at)?;
+        check_chain(chain, &specs.name, &specs.genesis_hash)?;
+        Ok(specs)
+    }
+
+    fn fetch_metadata(&self, chain: &Chain) -> Result<FetchedMeta> {
+        let meta = fetch_meta(
+            self.client(chain)?.as_ref(),
+            self.at,
+            chain.metadata_version,
+        )?;
+        check_chain(chain, &meta.meta_values.name, &meta.genesis_hash)?;
+        Ok(meta)
+    }
+}
+
+fn recording_path(dir: &Path, portal_id: &str) -> PathBuf {
+    dir.join(format!("{portal_id}.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn record_block_hash() {
+        let hash = format!("0x{}", "11".repeat(32));
+        let call = RecordedCall::new("wss://a", "chain_getBlockHash", vec![], json!(hash));
+        assert_eq!(call.block_hash, Some(H256::repeat_byte(0x11)));
+
+        let call = RecordedCall::new(
+            "wss://a",
+            "state_getMetadata",
+            vec![json!(hash)],
+            json!("0x"),
+        );
+        assert_eq!(call.block_hash, Some(H256::repeat_byte(0x11)));
+
+        let call = RecordedCall::new("wss://a", "system_properties", vec![], json!({}));
+        assert_eq!(call.block_hash, None);
+    }
+
+    #[test]
+    fn replay_last_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = Recorder::new(dir.path());
+        // A retried attempt and the one that succeeded
+        for (endpoint, head) in [("wss://a", "0x01"), ("wss://b", "0x02")] {
+            let call = RecordedCall::new(endpoint, "chain_getFinalizedHead", vec![], json!(head));
+            recorder.record("polkadot", call).unwrap();
+        }
+        let call = RecordedCall::new(
+            "wss://b",
+            "chain_getHeader",
+            vec![json!("0x02")],
+            json!({ "number": "0x2" }),
+        );
+        recorder.record("polkadot", call).unwrap();
+
+        let fetcher = ReplayFetcher::new(dir.path(), None);
+        let client = fetcher.client(&Chain::default()).unwrap();
+        for _ in 0..2 {
+            let head: String = client.request("chain_getFinalizedHead", vec![]).unwrap();
+            assert_eq!(head, "0x02");
+            assert!(client.call("chain_getHeader", vec![json!(head)]).is_ok());
+        }
+        assert!(client.call("chain_getHeader", vec![json!("0x01")]).is_err());
+        assert!(client.call("state_getMetadata", vec![]).is_err());
+    }
+}
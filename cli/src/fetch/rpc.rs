@@ -0,0 +1,311 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::{anyhow, ensure, Result};
+use blake2_rfc::blake2b::blake2b;
+use definitions::network_specs::NetworkSpecs;
+use log::warn;
+use parity_scale_codec::Encode;
+
+use crate::config::{AppConfig, Chain, Recording, RpcEndpoint, RpcSettings};
+use crate::fetch::client::{RpcClient, TransportError};
+use crate::fetch::node::{fetch_meta, fetch_network_specs, resolve_block, BlockId};
+use crate::fetch::record::Recorder;
+use crate::fetch::{check_chain, FetchedMeta, Fetcher};
+
+// The last error of an endpoint that failed after all retries
+struct EndpointFailure {
+    chain: String,
+    url: String,
+    reason: String,
+}
+
+pub(crate) struct RpcFetcher {
+    settings: RpcSettings,
+    failures: Mutex<Vec<EndpointFailure>>,
+    recorder: Option<Arc<Recorder>>,
+    // Finalized head if not set
+    at: Option<BlockId>,
+    // Resolved blocks by portal id
+    blocks: Mutex<HashMap<String, BlockId>>,
+}
+
+impl RpcFetcher {
+    pub(crate) fn new(config: &AppConfig) -> Self {
+        Self {
+            settings: config.rpc.clone(),
+            failures: Mutex::new(vec![]),
+            recorder: match &config.recording {
+                Some(Recording::Record(dir)) => Some(Arc::new(Recorder::new(dir))),
+                _ => None,
+            },
+            at: config.at,
+            blocks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Call endpoints one by one or all of them in quorum mode
+    fn call<F, T, K>(&self, chain: &Chain, f: F, fingerprint: K) -> Result<T>
+    where
+        F: Fn(&RpcEndpoint, &RpcSettings) -> Result<T>,
+        K: Fn(&T) -> String,
+    {
+        let settings = self.settings.merge(&chain.rpc);
+        let fetch = |endpoint: &RpcEndpoint| self.call_with_retries(chain, endpoint, &settings, &f);
+        match settings.quorum {
+            Some(threshold) => call_quorum(&chain.rpc_endpoints, threshold, fetch, fingerprint),
+            None => call_urls(&chain.rpc_endpoints, fetch),
+        }
+    }
+
+    // Call `f`, retrying transport errors and timeouts with exponential backoff.
+    // Other errors would be the same on every attempt
+    fn call_with_retries<F, T>(
+        &self,
+        chain: &Chain,
+        endpoint: &RpcEndpoint,
+        settings: &RpcSettings,
+        f: &F,
+    ) -> Result<T>
+    where
+        F: Fn(&RpcEndpoint, &RpcSettings) -> Result<T>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f(endpoint, settings) {
+                Ok(res) => return Ok(res),
+                Err(e) if is_transport_error(&e) && attempt < settings.retries() => {
+                    attempt += 1;
+                    let delay = settings.backoff(attempt);
+                    warn!("🔁 {} failed: {:#}. Retrying in {:?}", endpoint, e, delay);
+                    thread::sleep(delay);
+                }
+                Err(e) => {
+                    self.failures.lock().unwrap().push(EndpointFailure {
+                        chain: chain.portal_id(),
+                        url: endpoint.to_string(),
+                        reason: format!("{e:#}"),
+                    });
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    fn connect(
+        &self,
+        chain: &Chain,
+        endpoint: &RpcEndpoint,
+        settings: &RpcSettings,
+    ) -> Result<RpcClient> {
+        let client = RpcClient::connect(endpoint, settings)?;
+        Ok(match &self.recorder {
+            Some(recorder) => client.record(recorder.clone(), chain.portal_id()),
+            None => client,
+        })
+    }
+
+    // The finalized head, `--at` number or `--historical` version is resolved to a hash
+    // once per chain, so specs and metadata are taken at the same block and the binary
+    // search runs once. Retries and other endpoints reuse it
+    fn block(&self, chain: &Chain, client: &RpcClient) -> Result<Option<BlockId>> {
+        if let Some(BlockId::Hash(_)) = self.at {
+            return Ok(self.at);
+        }
+        if let Some(block) = self.blocks.lock().unwrap().get(&chain.portal_id()) {
+            return Ok(Some(*block));
+        }
+        let (hash, _) = resolve_block(client, self.at)?;
+        let block = BlockId::Hash(hash);
+        self.blocks.lock().unwrap().insert(chain.portal_id(), block);
+        Ok(Some(block))
+    }
+}
+
+impl Fetcher for RpcFetcher {
+    fn fetch_specs(&self, chain: &Chain) -> Result<NetworkSpecs> {
+        let fetch = |endpoint: &RpcEndpoint, settings: &RpcSettings| {
+            let client = self.connect(chain, endpoint, settings)?;
+            let at = self.block(chain, &client)?;
+            fetch_network_specs(&client, chain, at)
+        };
+        let specs = self.call(chain, fetch, specs_fingerprint)?;
+        check_chain(chain, &specs.name, &specs.genesis_hash)?;
+        Ok(specs)
+    }
+
+    fn fetch_metadata(&self, chain: &Chain) -> Result<FetchedMeta> {
+        let fetch = |endpoint: &RpcEndpoint, settings: &RpcSettings| {
+            let client = self.connect(chain, endpoint, settings)?;
+            let at = self.block(chain, &client)?;
+            fetch_meta(&client, at, chain.metadata_version)
+        };
+        let meta = self.call(chain, fetch, meta_fingerprint)?;
+        check_chain(chain, &meta.meta_values.name, &meta.genesis_hash)?;
+        Ok(meta)
+    }
+
+    // Summarise failed endpoints at the end of the command
+    fn report_failures(&self) {
+        let failures = std::mem::take(&mut *self.failures.lock().unwrap());
+        if failures.is_empty() {
+            return;
+        }
+        warn!("🤕 {} endpoint call(s) failed:", failures.len());
+        for failure in failures {
+            warn!("  {} {}: {}", failure.chain, failure.url, failure.reason);
+        }
+    }
+}
+
+fn is_transport_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<TransportError>().is_some()
+}
+
+// try to call all urls unless successful
+fn call_urls<F, T>(urls: &[RpcEndpoint], f: F) -> Result<T>
+where
+    F: Fn(&RpcEndpoint) -> Result<T>,
+{
+    let n = urls.len();
+    for url in urls.iter().take(n - 1) {
+        match f(url) {
+            Ok(res) => return Ok(res),
+            Err(e) => warn!("Failed to fetch {}: {:#}", url, e),
+        }
+    }
+    f(&urls[n - 1])
+}
+
+// Query every endpoint and return the response at least `threshold` of them agree on
+fn call_quorum<F, T, K>(urls: &[RpcEndpoint], threshold: usize, f: F, fingerprint: K) -> Result<T>
+where
+    F: Fn(&RpcEndpoint) -> Result<T>,
+    K: Fn(&T) -> String,
+{
+    // Every distinct response with endpoints that returned it
+    let mut responses: Vec<(String, T, Vec<&str>)> = vec![];
+    for url in urls {
+        match f(url) {
+            Ok(res) => {
+                let key = fingerprint(&res);
+                match responses.iter_mut().find(|(k, _, _)| *k == key) {
+                    Some((_, _, endpoints)) => endpoints.push(url.url.as_str()),
+                    None => responses.push((key, res, vec![url.url.as_str()])),
+                }
+            }
+            Err(e) => warn!("Failed to fetch {}: {:#}", url, e),
+        }
+    }
+    responses.sort_by_key(|(_, _, endpoints)| Reverse(endpoints.len()));
+
+    let mut responses = responses.into_iter();
+    let (agreed, res, endpoints) = responses
+        .next()
+        .ok_or_else(|| anyhow!("all endpoints failed"))?;
+    for (key, _, disagreeing) in responses {
+        for url in disagreeing {
+            warn!(
+                "⚠️  {} disagrees with other endpoints: {} instead of {}",
+                url, key, agreed
+            );
+        }
+    }
+    ensure!(
+        endpoints.len() >= threshold,
+        "only {} of {} endpoints agree on {}, {} required",
+        endpoints.len(),
+        urls.len(),
+        agreed,
+        threshold
+    );
+    Ok(res)
+}
+
+fn specs_fingerprint(specs: &NetworkSpecs) -> String {
+    format!(
+        "genesis 0x{}, specs 0x{}",
+        hex::encode(specs.genesis_hash),
+        hex::encode(blake2b(32, &[], &specs.encode()).as_bytes())
+    )
+}
+
+fn meta_fingerprint(meta: &FetchedMeta) -> String {
+    format!(
+        "genesis 0x{}, version {}, metadata 0x{}",
+        hex::encode(meta.genesis_hash),
+        meta.meta_values.version,
+        hex::encode(blake2b(32, &[], &meta.meta_values.meta).as_bytes())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls() -> Vec<RpcEndpoint> {
+        vec!["wss://a".into(), "wss://b".into(), "wss://c".into()]
+    }
+
+    fn fetch(endpoint: &RpcEndpoint) -> Result<u32> {
+        match endpoint.url.as_str() {
+            "wss://c" => Ok(9001),
+            _ => Ok(9000),
+        }
+    }
+
+    #[test]
+    fn quorum_reached() {
+        let result = call_quorum(&urls(), 2, fetch, |v| v.to_string()).unwrap();
+        assert_eq!(result, 9000);
+    }
+
+    #[test]
+    fn quorum_not_reached() {
+        let result = call_quorum(&urls(), 3, fetch, |v| v.to_string());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn retry_only_transport_errors() {
+        let fetcher = RpcFetcher::new(&AppConfig::default());
+        let settings = RpcSettings {
+            retries: Some(2),
+            backoff: Some(0),
+            ..RpcSettings::default()
+        };
+        let endpoint = RpcEndpoint::from("wss://a");
+        let attempts = Mutex::new(0);
+        let fail = |error: anyhow::Error| -> Result<()> {
+            *attempts.lock().unwrap() += 1;
+            Err(error)
+        };
+
+        let transport = |_: &RpcEndpoint, _: &RpcSettings| {
+            fail(anyhow!(TransportError("connection reset".to_string())))
+        };
+        let result = fetcher.call_with_retries(&Chain::default(), &endpoint, &settings, &transport);
+        assert!(result.is_err());
+        assert_eq!(*attempts.lock().unwrap(), 3);
+
+        *attempts.lock().unwrap() = 0;
+        let deterministic =
+            |_: &RpcEndpoint, _: &RpcSettings| fail(anyhow!("tokenSymbol has 2 values"));
+        let result =
+            fetcher.call_with_retries(&Chain::default(), &endpoint, &settings, &deterministic);
+        assert!(result.is_err());
+        assert_eq!(*attempts.lock().unwrap(), 1);
+        assert_eq!(fetcher.failures.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn fall_back_to_next_url() {
+        let result = call_urls(&urls(), |endpoint| match endpoint.url.as_str() {
+            "wss://a" => Err(anyhow!("timeout")),
+            _ => fetch(endpoint),
+        });
+        assert_eq!(result.unwrap(), 9000);
+    }
+}
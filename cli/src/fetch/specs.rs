@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use definitions::crypto::Encryption;
+use definitions::metadata::MetaValues;
+use definitions::network_specs::NetworkSpecs;
+use log::warn;
+use serde_json::{Map, Value};
+use sp_core::H256;
+
+use crate::config::Chain;
+
+// Colors used by Signer for networks added with generic specs
+const COLOR: &str = "#660D35";
+const SECONDARY_COLOR: &str = "#262626";
+
+const DEFAULT_DECIMALS: u8 = 0;
+const DEFAULT_UNIT: &str = "UNIT";
+
+// Build network specs from the node properties, the same way Signer tools do
+pub(crate) fn build_specs(
+    chain: &Chain,
+    meta_values: &MetaValues,
+    genesis_hash: H256,
+    properties: &Map<String, Value>,
+) -> Result<NetworkSpecs> {
+    let base58prefix = base58prefix(properties, meta_values.optional_base58prefix)?;
+    let (decimals, unit) = match (chain.token_decimals, &chain.token_unit) {
+        (Some(decimals), Some(unit)) => (decimals, unit.clone()),
+        _ => (token_decimals(properties)?, token_unit(properties)?),
+    };
+    let encryption: Encryption = chain.encryption().into();
+    let name = meta_values.name.clone();
+    Ok(NetworkSpecs {
+        base58prefix,
+        color: COLOR.to_string(),
+        decimals,
+        title: format!("{}-{}", name, encryption.show()),
+        encryption,
+        genesis_hash,
+        logo: name.clone(),
+        path_id: format!("//{name}"),
+        secondary_color: SECONDARY_COLOR.to_string(),
+        unit,
+        name,
+    })
+}
+
+fn base58prefix(properties: &Map<String, Value>, from_meta: Option<u16>) -> Result<u16> {
+    let from_properties = match properties.get("ss58Format") {
+        Some(value) => match value.as_u64().map(u16::try_from) {
+            Some(Ok(prefix)) => Some(prefix),
+            _ => bail!("unexpected ss58Format {}", value),
+        },
+        None => None,
+    };
+    match (from_properties, from_meta) {
+        (Some(a), Some(b)) if a != b => {
+            bail!(
+                "base58 prefix mismatch: {} in properties, {} in metadata",
+                a,
+                b
+            )
+        }
+        (Some(prefix), _) | (None, Some(prefix)) => Ok(prefix),
+        (None, None) => bail!("no base58 prefix in properties or metadata"),
+    }
+}
+
+fn token_decimals(properties: &Map<String, Value>) -> Result<u8> {
+    match single_value(properties, "tokenDecimals")? {
+        Some(value) => match value.as_u64().map(u8::try_from) {
+            Some(Ok(decimals)) => Ok(decimals),
+            _ => bail!("unexpected tokenDecimals {}", value),
+        },
+        None => {
+            warn!("No tokenDecimals in properties, using {}", DEFAULT_DECIMALS);
+            Ok(DEFAULT_DECIMALS)
+        }
+    }
+}
+
+fn token_unit(properties: &Map<String, Value>) -> Result<String> {
+    match single_value(properties, "tokenSymbol")? {
+        Some(value) => match value.as_str() {
+            Some(unit) => Ok(unit.to_string()),
+            None => bail!("unexpected tokenSymbol {}", value),
+        },
+        None => {
+            warn!("No tokenSymbol in properties, using {}", DEFAULT_UNIT);
+            Ok(DEFAULT_UNIT.to_string())
+        }
+    }
+}
+
+// Token properties are either a value or an array of values for multi-token chains
+fn single_value<'a>(properties: &'a Map<String, Value>, key: &str) -> Result<Option<&'a Value>> {
+    match properties.get(key) {
+        Some(Value::Array(values)) => match values.as_slice() {
+            [value] => Ok(Some(value)),
+            _ => bail!(
+                "{} has {} values. Please set `token_unit` and `token_decimals` in `config.toml`",
+                key,
+                values.len()
+            ),
+        },
+        value => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn properties(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn single_token_in_array() {
+        let properties = properties(json!({"tokenDecimals": [10], "tokenSymbol": ["DOT"]}));
+        assert_eq!(token_decimals(&properties).unwrap(), 10);
+        assert_eq!(token_unit(&properties).unwrap(), "DOT");
+    }
+
+    #[test]
+    fn multiple_tokens_need_override() {
+        let properties =
+            properties(json!({"tokenDecimals": [12, 12], "tokenSymbol": ["KAR", "KUSD"]}));
+        assert!(token_decimals(&properties).is_err());
+        assert!(token_unit(&properties).is_err());
+    }
+
+    #[test]
+    fn base58prefix_mismatch() {
+        let properties = properties(json!({"ss58Format": 0}));
+        assert_eq!(base58prefix(&properties, Some(0)).unwrap(), 0);
+        assert_eq!(base58prefix(&properties, None).unwrap(), 0);
+        assert!(base58prefix(&properties, Some(2)).is_err());
+    }
+}
@@ -13,7 +13,7 @@ pub(crate) fn files_to_keep(config: &AppConfig) -> anyhow::Result<HashSet<QrPath
     let all_specs = spec_files(&config.qr_dir)?;
     let chain_specs = read_export_file(config)?;
 
-    for chain in &config.chains {
+    for chain in config.selected_chains() {
         let latest_version = match &chain_specs
             .get(&chain.portal_id())
             .context(format!("No data found for {}", chain.portal_id()))?
@@ -1,7 +1,10 @@
+mod chain_editor;
 mod cleaner;
 mod collector;
 mod common;
 mod config;
+mod config_validator;
+mod cross_checker;
 mod deployment_checker;
 mod ethereum;
 mod export;
@@ -18,44 +21,89 @@ use std::process::exit;
 
 use clap::Parser;
 use env_logger::Env;
-use log::error;
+use log::{error, info};
 
+use crate::chain_editor::{add_chain, remove_chain, rename_chain};
 use crate::cleaner::clean;
 use crate::collector::collect;
-use crate::config::AppConfig;
+use crate::common::logger::CapturingLogger;
+use crate::config::{show_config, AppConfig};
+use crate::config_validator::validate_config;
+use crate::cross_checker::cross_check;
 use crate::deployment_checker::check_deployment;
-use crate::fetch::RpcFetcher;
-use crate::opts::{Opts, SubCommand};
+use crate::fetch::{node_fetcher, FileFetcher, RpcFetcher};
+use crate::opts::{ConfigCommand, Opts, SubCommand};
 use crate::signer::sign;
-use crate::updater::source::UpdateSource;
-use crate::updater::{update_from_github, update_from_node};
+use crate::updater::{update, UpdateFetchers};
 use crate::verifier::verify;
 
 /// Main entry point of the `metadata-cli`
 fn main() {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info"))
-        .format_target(false)
-        .init();
+    CapturingLogger::init(
+        env_logger::Builder::from_env(Env::default().default_filter_or("info"))
+            .format_target(false)
+            .build(),
+    );
 
     let opts: Opts = Opts::parse();
-    let config = match AppConfig::load(opts.config) {
+    let mut config = match AppConfig::load(&opts.config) {
         Ok(config) => config,
         Err(err) => {
             error!("{}", err);
             exit(1);
         }
     };
+    if let Some(jobs) = opts.jobs {
+        config.jobs = jobs;
+    }
+    config.cache.mode = opts.cache_mode();
+    config.recording = opts.recording();
+    if let Err(err) = validate_config(&config) {
+        error!("{}", err);
+        exit(1);
+    }
+    if let Some(filter) = opts.subcmd.chain_filter() {
+        if let Err(err) = config.set_filter(filter) {
+            error!("{}", err);
+            exit(1);
+        }
+    }
+    if let Some(at) = opts.subcmd.block() {
+        if let Err(err) = config.set_block(at) {
+            error!("{}", err);
+            exit(1);
+        }
+    }
 
     let result = match opts.subcmd {
-        SubCommand::Clean => clean(config),
-        SubCommand::Collect => collect(config),
-        SubCommand::Sign => sign(config),
-        SubCommand::Verify => verify(config),
-        SubCommand::Update(update_opts) => match update_opts.source {
-            UpdateSource::Github => update_from_github(config),
-            UpdateSource::Node => update_from_node(config, RpcFetcher),
-        },
+        SubCommand::Clean(_) => clean(config),
+        SubCommand::Collect(collect_opts) => collect(config, collect_opts.dir),
+        SubCommand::Sign(_) => sign(config),
+        SubCommand::Verify(_) => verify(config),
+        SubCommand::Update(update_opts) => {
+            let releases = update_opts.release_selection();
+            let fetchers = UpdateFetchers {
+                node: node_fetcher(&config),
+                files: update_opts.dir.map(FileFetcher::new),
+            };
+            update(config, update_opts.source, fetchers, releases)
+        }
         SubCommand::CheckDeployment => check_deployment(config),
+        SubCommand::CrossCheck(_) => {
+            // An independent check must not trust cached node data
+            let fetcher = RpcFetcher::new(&config);
+            cross_check(config, fetcher)
+        }
+        SubCommand::ValidateConfig => {
+            info!("✅ Config is valid");
+            Ok(())
+        }
+        SubCommand::AddChain(add_opts) => add_chain(config, &opts.config, add_opts),
+        SubCommand::RemoveChain(remove_opts) => remove_chain(config, &opts.config, remove_opts),
+        SubCommand::RenameChain(rename_opts) => rename_chain(config, &opts.config, rename_opts),
+        SubCommand::Config(ConfigCommand::Show { resolved }) => {
+            show_config(&opts.config, &config, resolved)
+        }
     };
 
     if let Err(err) = result {
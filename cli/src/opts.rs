@@ -1,7 +1,10 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 
+use crate::config::{CacheMode, ChainFilter, Recording};
+use crate::fetch::BlockId;
+use crate::updater::github::{ReleaseSelection, MAX_RELEASES};
 use crate::updater::source::UpdateSource;
 
 #[derive(Parser)]
@@ -10,34 +13,243 @@ pub(crate) struct Opts {
     #[arg(short, long, default_value = "config.toml")]
     pub(crate) config: PathBuf,
 
+    /// Number of chains fetched concurrently. Overrides `jobs` from config
+    #[arg(short, long, global = true)]
+    pub(crate) jobs: Option<usize>,
+
+    /// Use cached specs and metadata only, never touch RPC endpoints
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    pub(crate) offline: bool,
+
+    /// Ignore cached specs and metadata and fetch them again
+    #[arg(long, global = true)]
+    pub(crate) refresh: bool,
+
+    /// Save every RPC response to the directory
+    #[arg(long, global = true, conflicts_with = "replay")]
+    pub(crate) record: Option<PathBuf>,
+
+    /// Serve RPC responses saved with `--record` from the directory
+    #[arg(long, global = true)]
+    pub(crate) replay: Option<PathBuf>,
+
     #[clap(subcommand)]
     pub(crate) subcmd: SubCommand,
 }
 
+impl Opts {
+    pub(crate) fn cache_mode(&self) -> CacheMode {
+        match (self.offline, self.refresh) {
+            (true, _) => CacheMode::Offline,
+            (_, true) => CacheMode::Refresh,
+            _ => CacheMode::Default,
+        }
+    }
+
+    pub(crate) fn recording(&self) -> Option<Recording> {
+        match (&self.record, &self.replay) {
+            (Some(dir), _) => Some(Recording::Record(dir.clone())),
+            (_, Some(dir)) => Some(Recording::Replay(dir.clone())),
+            _ => None,
+        }
+    }
+}
+
 /// You can find all available commands below.
 #[derive(Subcommand)]
 pub(crate) enum SubCommand {
     /// Remove unused QR codes
-    Clean,
+    Clean(ChainFilterOpts),
 
     /// Generate json data file for frontend
-    Collect,
+    Collect(CollectOpts),
 
     /// Sign unsigned QR codes.
-    Sign,
+    Sign(ChainFilterOpts),
 
     /// Check updates
     Update(UpdateOpts),
 
     /// Verify signed QR codes
-    Verify,
+    Verify(ChainFilterOpts),
 
     /// Check if deployment is up to date
     CheckDeployment,
+
+    /// Compare metadata of the latest GitHub release with metadata from the node
+    CrossCheck(ChainFilterOpts),
+
+    /// Check config file for semantic errors
+    ValidateConfig,
+
+    /// Add a new chain to config file using its RPC endpoint
+    AddChain(AddChainOpts),
+
+    /// Remove chain from config file together with its QR codes
+    RemoveChain(RemoveChainOpts),
+
+    /// Change chain name or relay chain and migrate its QR codes
+    RenameChain(RenameChainOpts),
+
+    /// Inspect config file
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand)]
+pub(crate) enum ConfigCommand {
+    /// Print config file
+    Show {
+        /// Print the result of merging included files and env overrides
+        #[arg(long)]
+        resolved: bool,
+    },
+}
+
+impl SubCommand {
+    pub(crate) fn chain_filter(&self) -> Option<ChainFilter> {
+        match self {
+            SubCommand::Clean(opts)
+            | SubCommand::Sign(opts)
+            | SubCommand::Verify(opts)
+            | SubCommand::CrossCheck(opts) => Some(opts.clone().into()),
+            SubCommand::Collect(opts) => Some(opts.filter.clone().into()),
+            SubCommand::Update(opts) => Some(opts.filter.clone().into()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn block(&self) -> Option<BlockId> {
+        match self {
+            SubCommand::Update(opts) => opts.at.or(opts.historical.map(BlockId::SpecVersion)),
+            _ => None,
+        }
+    }
+}
+
+/// Select chains to work with. All chains are used by default
+#[derive(Args, Clone)]
+pub(crate) struct ChainFilterOpts {
+    /// Portal id of the chain, e.g. `polkadot-statemint`. Can be repeated
+    #[arg(long = "chain")]
+    pub(crate) chains: Vec<String>,
+
+    /// Relay chain and all its parachains. Can be repeated
+    #[arg(long = "relay")]
+    pub(crate) relays: Vec<String>,
+
+    /// Chains with the tag from config. Can be repeated
+    #[arg(long = "tag")]
+    pub(crate) tags: Vec<String>,
+}
+
+impl From<ChainFilterOpts> for ChainFilter {
+    fn from(opts: ChainFilterOpts) -> Self {
+        Self {
+            chains: opts.chains,
+            relays: opts.relays,
+            tags: opts.tags,
+        }
+    }
 }
 
 #[derive(Parser)]
 pub(crate) struct UpdateOpts {
-    #[arg(short, long, default_value = "node")]
-    pub(crate) source: UpdateSource,
+    /// Use only this source for every chain instead of `update_sources` from config
+    #[arg(short, long)]
+    pub(crate) source: Option<UpdateSource>,
+
+    /// Directory with `<portal_id>/specs.json` and metadata files for the `files` source
+    #[arg(long, required_if_eq("source", "files"))]
+    pub(crate) dir: Option<PathBuf>,
+
+    /// Block hash or number to fetch data at instead of the finalized head. Requires a single chain
+    #[arg(long)]
+    pub(crate) at: Option<BlockId>,
+
+    /// Fetch metadata of an older spec version from an archive node. Requires a single chain
+    #[arg(long, conflicts_with = "at")]
+    pub(crate) historical: Option<u32>,
+
+    /// Take runtimes from the GitHub release with this tag instead of the latest one
+    #[arg(long, conflicts_with = "releases")]
+    pub(crate) tag: Option<String>,
+
+    /// Generate missing metadata from the last N GitHub releases
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=MAX_RELEASES as i64))]
+    pub(crate) releases: Option<u8>,
+
+    #[command(flatten)]
+    pub(crate) filter: ChainFilterOpts,
+}
+
+impl UpdateOpts {
+    pub(crate) fn release_selection(&self) -> ReleaseSelection {
+        match (&self.tag, self.releases) {
+            (Some(tag), _) => ReleaseSelection::Tag(tag.clone()),
+            (_, Some(n)) => ReleaseSelection::Last(n),
+            _ => ReleaseSelection::Latest,
+        }
+    }
+}
+
+#[derive(Parser)]
+pub(crate) struct CollectOpts {
+    /// Read specs and metadata from `<portal_id>/` subdirectories instead of RPC
+    #[arg(long)]
+    pub(crate) dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub(crate) filter: ChainFilterOpts,
+}
+
+#[derive(Parser)]
+pub(crate) struct AddChainOpts {
+    /// RPC endpoint of the chain
+    #[arg(long)]
+    pub(crate) rpc: String,
+
+    /// Name of the relay chain for parachains
+    #[arg(long)]
+    pub(crate) relay: Option<String>,
+
+    /// Title shown on the portal
+    #[arg(long)]
+    pub(crate) title: Option<String>,
+
+    /// Chain color in #RRGGBB format
+    #[arg(long)]
+    pub(crate) color: Option<String>,
+
+    /// Generate unsigned specs and metadata QR codes
+    #[arg(long)]
+    pub(crate) generate: bool,
+}
+
+#[derive(Parser)]
+pub(crate) struct RemoveChainOpts {
+    /// Portal id of the chain, e.g. `polkadot-statemint`
+    pub(crate) portal_id: String,
+
+    /// Only print what is going to change
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Parser)]
+pub(crate) struct RenameChainOpts {
+    /// Portal id of the chain, e.g. `polkadot-statemint`
+    pub(crate) portal_id: String,
+
+    /// New chain name
+    #[arg(long)]
+    pub(crate) name: Option<String>,
+
+    /// New relay chain
+    #[arg(long)]
+    pub(crate) relay: Option<String>,
+
+    /// Only print what is going to change
+    #[arg(long)]
+    pub(crate) dry_run: bool,
 }
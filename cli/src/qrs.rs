@@ -7,7 +7,7 @@ use anyhow::{Context, Result};
 use crate::common::path::{ContentType, QrPath};
 use crate::common::types::{ChainPortalId, MetaVersion};
 
-type MetadataMap = HashMap<ChainPortalId, BTreeMap<MetaVersion, QrPath>>;
+pub(crate) type MetadataMap = HashMap<ChainPortalId, BTreeMap<MetaVersion, QrPath>>;
 
 /// QR dir content
 pub(crate) fn qrs_in_dir(dir: impl AsRef<Path>) -> Result<Vec<QrPath>> {
@@ -6,7 +6,6 @@ use std::ops::Deref;
 use std::process::Command;
 
 use anyhow::{anyhow, bail};
-use definitions::crypto::Encryption;
 use definitions::error::TransferContent;
 use generate_message::full_run;
 use generate_message::parser::{
@@ -17,8 +16,7 @@ use transaction_parsing::check_signature::pass_crypto;
 
 use crate::common::camera::read_qr_file;
 use crate::common::path::{ContentType, QrPath};
-use crate::config::AppConfig;
-use crate::ethereum::is_ethereum;
+use crate::config::{AppConfig, Chain, EncryptionType};
 use crate::file::files_to_keep;
 use crate::signer::prompt::{select_file, want_to_continue};
 use crate::source::{read_png_source, save_source_info};
@@ -34,14 +32,26 @@ pub(crate) fn sign(config: AppConfig) -> anyhow::Result<()> {
         _ => {
             while !files_to_sign.is_empty() {
                 let i = select_file(&files_to_sign);
-                run_for_file(&files_to_sign.swap_remove(i))?
+                let qr_path = files_to_sign.swap_remove(i);
+                let encryption = chain_encryption(&config, &qr_path);
+                run_for_file(&qr_path, encryption)?
             }
         }
     }
     Ok(())
 }
 
-fn run_for_file(qr_path: &QrPath) -> anyhow::Result<()> {
+// Encryption of the chain the QR belongs to
+fn chain_encryption(config: &AppConfig, qr_path: &QrPath) -> EncryptionType {
+    config
+        .chains
+        .iter()
+        .find(|c| c.portal_id() == qr_path.file_name.chain)
+        .map(Chain::encryption)
+        .unwrap_or_default()
+}
+
+fn run_for_file(qr_path: &QrPath, encryption: EncryptionType) -> anyhow::Result<()> {
     open_in_browser(qr_path)?;
 
     if !want_to_continue() {
@@ -54,12 +64,16 @@ fn run_for_file(qr_path: &QrPath) -> anyhow::Result<()> {
         Err(e) => bail!("QR reading error. {}", e),
     };
 
-    sign_qr(qr_path, signature)?;
+    sign_qr(qr_path, signature, encryption)?;
     println!("🎉 Signed!");
     Ok(())
 }
 
-fn sign_qr(unsigned_qr: &QrPath, signature: String) -> anyhow::Result<QrPath> {
+fn sign_qr(
+    unsigned_qr: &QrPath,
+    signature: String,
+    encryption: EncryptionType,
+) -> anyhow::Result<QrPath> {
     let mut signed_qr = unsigned_qr.clone();
     signed_qr.file_name.is_signed = true;
 
@@ -79,10 +93,6 @@ fn sign_qr(unsigned_qr: &QrPath, signature: String) -> anyhow::Result<QrPath> {
     let mut f = File::create(&content_file)?;
     f.write_all(passed_crypto.message.deref())?;
 
-    let signing_algorithm = match is_ethereum(&signed_qr.file_name.chain) {
-        true => Encryption::Ethereum,
-        false => Encryption::Sr25519,
-    };
     let make = Make {
         goal: Goal::Qr,
         verifier: Verifier {
@@ -103,7 +113,7 @@ fn sign_qr(unsigned_qr: &QrPath, signature: String) -> anyhow::Result<QrPath> {
         files_dir: signed_qr.dir.clone(),
         payload: content_file,
         export_dir: signed_qr.dir.clone(),
-        crypto: Some(signing_algorithm),
+        crypto: Some(encryption.into()),
     };
     println!("⚙ generating {signed_qr}...");
     full_run(SignerCommand::Make(make)).map_err(|e| anyhow!("{:?}", e))?;
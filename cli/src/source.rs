@@ -13,8 +13,45 @@ const SOURCE: &str = "Source";
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 #[serde(tag = "type")]
 pub(crate) enum Source {
-    Wasm { github_repo: String, hash: String },
-    Rpc { block: H256 },
+    Wasm {
+        github_repo: String,
+        /// Hash of the published release asset
+        hash: String,
+        /// Hash of the runtime code, differs from `hash` if the asset is compressed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code_hash: Option<String>,
+        /// Published digest the asset was verified against, e.g. `blake2_256:0x…`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        checksum: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        srtool: Option<SrtoolInfo>,
+        /// Metadata format version, e.g. 14 for V14
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata_format: Option<u8>,
+    },
+    Rpc {
+        block: H256,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        number: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata_format: Option<u8>,
+    },
+}
+
+/// Build information from the srtool output published with the runtime
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub(crate) struct SrtoolInfo {
+    /// srtool version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) gen: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) rustc: Option<String>,
+    /// Runtime package name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) pkg: Option<String>,
+    /// Hash of the runtime upgrade proposal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) prop: Option<String>,
 }
 
 // Add `Source` info to png file as a zTXt chunk
@@ -87,6 +124,8 @@ mod tests {
 
         let source = Source::Rpc {
             block: H256::default(),
+            number: Some(42),
+            metadata_format: Some(14),
         };
         save_source_info(test_png, &source).unwrap();
         let result = read_png_source(test_png).unwrap().unwrap();
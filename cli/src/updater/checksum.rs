@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use anyhow::{anyhow, ensure, Context, Result};
+use blake2_rfc::blake2b::blake2b;
+use log::{info, warn};
+use serde::Deserialize;
+use sp_core::hashing::sha2_256;
+
+use crate::source::SrtoolInfo;
+use crate::updater::wasm::{ChecksumAsset, WasmRuntime};
+
+// Extensions of plain text digest assets published next to the runtime
+pub(crate) const DIGEST_EXTENSIONS: [&str; 3] = [".blake2_256", ".blake2", ".sha256"];
+
+/// Published digest the downloaded runtime matched
+#[derive(Debug, PartialEq)]
+pub(crate) struct VerifiedDigest {
+    /// `<algorithm>:0x<digest>`, e.g. `blake2_256:0x…`
+    pub(crate) digest: String,
+    pub(crate) srtool: Option<SrtoolInfo>,
+}
+
+/// Downloaded runtime differs from a digest published in its release.
+/// Unlike other source errors it fails the update instead of falling back to the next source
+#[derive(Debug)]
+pub(crate) struct ChecksumMismatch(pub(crate) String);
+
+impl Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+// Subset of the srtool JSON output
+#[derive(Deserialize)]
+struct SrtoolOutput {
+    gen: Option<String>,
+    rustc: Option<String>,
+    pkg: Option<String>,
+    prop: Option<String>,
+    #[serde(default)]
+    runtimes: HashMap<String, SrtoolRuntime>,
+}
+
+#[derive(Deserialize)]
+struct SrtoolRuntime {
+    blake2_256: Option<String>,
+    sha256: Option<String>,
+}
+
+// Check the downloaded runtime against every checksum asset of the release.
+// Fails on any mismatch, returns None if the release has no checksums
+pub(crate) async fn verify_wasm(
+    wasm: &WasmRuntime,
+    wasm_bytes: &[u8],
+) -> Result<Option<VerifiedDigest>> {
+    if wasm.checksums.is_empty() {
+        warn!("⚠️  No checksums published for {} runtime", wasm.chain);
+        return Ok(None);
+    }
+    let mut verified = None;
+    for asset in &wasm.checksums {
+        let content = download_checksum(asset).await?;
+        let digest = check_digest(&asset.name, &content, wasm_bytes)?;
+        info!("🔐 {} matches {}", asset.name, digest.digest);
+        // srtool output carries more information than plain digests
+        if verified.is_none() || digest.srtool.is_some() {
+            verified = Some(digest);
+        }
+    }
+    Ok(verified)
+}
+
+async fn download_checksum(asset: &ChecksumAsset) -> Result<String> {
+    let response = reqwest::get(asset.download_url.clone()).await?;
+    ensure!(
+        response.status().is_success(),
+        "failed to download {}: {}",
+        asset.download_url,
+        response.status()
+    );
+    Ok(response.text().await?)
+}
+
+fn check_digest(name: &str, content: &str, wasm_bytes: &[u8]) -> Result<VerifiedDigest> {
+    let blake2_256 = hex_digest(blake2b(32, &[], wasm_bytes).as_bytes());
+    let sha256 = hex_digest(&sha2_256(wasm_bytes));
+
+    if name.ends_with(".json") {
+        let output: SrtoolOutput =
+            serde_json::from_str(content).context(format!("invalid srtool output {name}"))?;
+        let matches = |expected: &Option<String>, actual: &str| {
+            expected.as_deref().map(normalize).as_deref() == Some(actual)
+        };
+        let digest = output.runtimes.values().find_map(|runtime| {
+            if matches(&runtime.blake2_256, &blake2_256) {
+                Some(format!("blake2_256:{blake2_256}"))
+            } else if matches(&runtime.sha256, &sha256) {
+                Some(format!("sha256:{sha256}"))
+            } else {
+                None
+            }
+        });
+        return match digest {
+            Some(digest) => Ok(VerifiedDigest {
+                digest,
+                srtool: Some(SrtoolInfo {
+                    gen: output.gen,
+                    rustc: output.rustc,
+                    pkg: output.pkg,
+                    prop: output.prop,
+                }),
+            }),
+            None => Err(anyhow!(ChecksumMismatch(format!(
+                "downloaded runtime does not match {name}: blake2_256 {blake2_256}, sha256 {sha256}"
+            )))),
+        };
+    }
+
+    // `<digest>` or `<digest>  <file name>`
+    let expected = content
+        .split_whitespace()
+        .next()
+        .map(normalize)
+        .ok_or_else(|| anyhow!("{} is empty", name))?;
+    let (algorithm, actual) = match name.ends_with(".sha256") {
+        true => ("sha256", sha256),
+        false => ("blake2_256", blake2_256),
+    };
+    if expected != actual {
+        return Err(anyhow!(ChecksumMismatch(format!(
+            "downloaded runtime does not match {name}: {algorithm} {actual} instead of {expected}"
+        ))));
+    }
+    Ok(VerifiedDigest {
+        digest: format!("{algorithm}:{actual}"),
+        srtool: None,
+    })
+}
+
+fn hex_digest(digest: &[u8]) -> String {
+    format!("0x{}", hex::encode(digest))
+}
+
+fn normalize(digest: &str) -> String {
+    format!("0x{}", digest.trim_start_matches("0x").to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WASM: &[u8] = b"\0asm runtime";
+
+    #[test]
+    fn check_plain_digests() {
+        let blake2_256 = hex::encode(blake2b(32, &[], WASM).as_bytes());
+        let content = format!("{blake2_256}  polkadot.wasm\n");
+        let verified = check_digest("polkadot.wasm.blake2_256", &content, WASM).unwrap();
+        assert_eq!(verified.digest, format!("blake2_256:0x{blake2_256}"));
+
+        let sha256 = format!("0x{}", hex::encode(sha2_256(WASM)).to_uppercase());
+        assert!(check_digest("polkadot.wasm.sha256", &sha256, WASM).is_ok());
+        let mismatch = check_digest("polkadot.wasm.sha256", &blake2_256, WASM).unwrap_err();
+        assert!(mismatch.is::<ChecksumMismatch>());
+        let empty = check_digest("polkadot.wasm.blake2_256", "", WASM).unwrap_err();
+        assert!(!empty.is::<ChecksumMismatch>());
+    }
+
+    #[test]
+    fn check_srtool_output() {
+        let blake2_256 = hex_digest(blake2b(32, &[], WASM).as_bytes());
+        let output = format!(
+            r#"{{
+                "gen": "srtool v0.13.0",
+                "rustc": "rustc 1.69.0",
+                "pkg": "polkadot-runtime",
+                "runtimes": {{
+                    "compact": {{ "blake2_256": "0x00" }},
+                    "compressed": {{ "blake2_256": "{blake2_256}" }}
+                }}
+            }}"#
+        );
+        let verified = check_digest("polkadot_srtool_output.json", &output, WASM).unwrap();
+        assert_eq!(verified.digest, format!("blake2_256:{blake2_256}"));
+        assert_eq!(verified.srtool.unwrap().pkg.unwrap(), "polkadot-runtime");
+
+        let output = r#"{ "runtimes": { "compressed": { "blake2_256": "0x00" } } }"#;
+        let mismatch = check_digest("polkadot_srtool_output.json", output, WASM).unwrap_err();
+        assert!(mismatch.is::<ChecksumMismatch>());
+    }
+}
@@ -1,30 +1,184 @@
 use std::env;
 
+use anyhow::Context;
+use octocrab::models::repos::{Asset, Release};
 use octocrab::Octocrab;
+use regex::Regex;
 
-use crate::config::GithubRepo;
-use crate::updater::wasm::WasmRuntime;
+use crate::config::{GithubRepo, ReleaseChannel};
+use crate::updater::checksum::DIGEST_EXTENSIONS;
+use crate::updater::wasm::{ChecksumAsset, WasmRuntime, DEFAULT_ASSET_PATTERN};
+
+// GitHub API returns at most 100 releases per page
+pub(crate) const MAX_RELEASES: u8 = 100;
+
+/// Releases to take runtimes from
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum ReleaseSelection {
+    /// The newest release of the chain channel
+    #[default]
+    Latest,
+    /// Release with the tag, regardless of the channel
+    Tag(String),
+    /// The last N releases of the chain channel
+    Last(u8),
+}
 
 // fetch the latest chain runtime from GitHub
 pub(crate) async fn fetch_latest_runtime(
     config: &GithubRepo,
     chain: &str,
 ) -> anyhow::Result<Option<WasmRuntime>> {
+    let runtimes = fetch_runtimes(config, chain, &ReleaseSelection::Latest).await?;
+    Ok(runtimes.into_iter().next())
+}
+
+// fetch chain runtimes of the selected releases, newest first.
+// Releases without a runtime of the chain are skipped
+pub(crate) async fn fetch_runtimes(
+    config: &GithubRepo,
+    chain: &str,
+    selection: &ReleaseSelection,
+) -> anyhow::Result<Vec<WasmRuntime>> {
     let github = match env::var("GITHUB_TOKEN") {
         Ok(token) => Octocrab::builder().personal_token(token).build()?,
         Err(_) => Octocrab::default(),
     };
-    let release = github
+    let releases = match selection {
+        ReleaseSelection::Tag(tag) => vec![github
+            .repos(&config.owner, &config.repo)
+            .releases()
+            .get_by_tag(tag)
+            .await
+            .context(format!("release {tag} not found"))?],
+        ReleaseSelection::Latest if config.channel == ReleaseChannel::Latest => vec![
+            github
+                .repos(&config.owner, &config.repo)
+                .releases()
+                .get_latest()
+                .await?,
+        ],
+        ReleaseSelection::Latest => list_releases(&github, config, 1).await?,
+        ReleaseSelection::Last(n) => list_releases(&github, config, *n).await?,
+    };
+
+    let pattern = asset_pattern(config)?;
+    let chain = config.asset_chain.as_deref().unwrap_or(chain);
+    Ok(releases
+        .into_iter()
+        .filter_map(|release| find_runtime(release.assets, &pattern, chain))
+        .collect())
+}
+
+// The last `n` published releases. Pre-releases are only included in the `prerelease` channel
+async fn list_releases(
+    github: &Octocrab,
+    config: &GithubRepo,
+    n: u8,
+) -> anyhow::Result<Vec<Release>> {
+    let page = github
         .repos(&config.owner, &config.repo)
         .releases()
-        .get_latest()
+        .list()
+        .per_page(MAX_RELEASES)
+        .send()
         .await?;
-    for asset in release.assets {
-        if let Ok(wasm) = WasmRuntime::try_from(asset) {
-            if wasm.chain == chain {
-                return Ok(Some(wasm));
-            }
+    Ok(page
+        .items
+        .into_iter()
+        .filter(|release| is_in_channel(release.draft, release.prerelease, config.channel))
+        .take(n as usize)
+        .collect())
+}
+
+// Drafts are never used, pre-releases only in the `prerelease` channel
+fn is_in_channel(draft: bool, prerelease: bool, channel: ReleaseChannel) -> bool {
+    !draft && (channel == ReleaseChannel::Prerelease || !prerelease)
+}
+
+fn find_runtime(assets: Vec<Asset>, pattern: &Regex, chain: &str) -> Option<WasmRuntime> {
+    // Releases may contain other files and runtimes of other chains
+    let (name, mut wasm) = assets.iter().find_map(|asset| {
+        WasmRuntime::from_asset(asset, pattern)
+            .ok()
+            .filter(|wasm| wasm.chain == chain)
+            .map(|wasm| (asset.name.clone(), wasm))
+    })?;
+    wasm.checksums = assets
+        .into_iter()
+        .filter(|asset| is_checksum_asset(&asset.name, &name, chain))
+        .map(|asset| ChecksumAsset {
+            name: asset.name,
+            download_url: asset.browser_download_url,
+        })
+        .collect();
+    Some(wasm)
+}
+
+// `<wasm asset>.blake2_256`, `<wasm asset>.sha256` or srtool JSON output of the chain,
+// e.g. `polkadot_srtool_output.json`
+fn is_checksum_asset(name: &str, wasm_name: &str, chain: &str) -> bool {
+    if let Some(extension) = name.strip_prefix(wasm_name) {
+        return DIGEST_EXTENSIONS.contains(&extension);
+    }
+    match name.strip_prefix(chain) {
+        Some(rest) => {
+            (rest.starts_with('_') || rest.starts_with('.') || rest.starts_with("-srtool"))
+                && rest.contains("srtool")
+                && rest.ends_with(".json")
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn asset_pattern(config: &GithubRepo) -> anyhow::Result<Regex> {
+    let pattern = config
+        .asset_pattern
+        .as_deref()
+        .unwrap_or(DEFAULT_ASSET_PATTERN);
+    Regex::new(pattern).context(format!("invalid asset pattern {pattern}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_releases_by_channel() {
+        // draft, prerelease, channel, expected
+        for (draft, prerelease, channel, expected) in [
+            (false, false, ReleaseChannel::Latest, true),
+            (false, true, ReleaseChannel::Latest, false),
+            (true, false, ReleaseChannel::Latest, false),
+            (false, false, ReleaseChannel::Prerelease, true),
+            (false, true, ReleaseChannel::Prerelease, true),
+            (true, true, ReleaseChannel::Prerelease, false),
+        ] {
+            assert_eq!(
+                is_in_channel(draft, prerelease, channel),
+                expected,
+                "draft {draft}, prerelease {prerelease}, {channel:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn match_checksum_assets() {
+        let wasm = "polkadot_runtime-v9370.compact.compressed.wasm";
+        for name in [
+            "polkadot_runtime-v9370.compact.compressed.wasm.blake2_256",
+            "polkadot_runtime-v9370.compact.compressed.wasm.sha256",
+            "polkadot_srtool_output.json",
+        ] {
+            assert!(is_checksum_asset(name, wasm, "polkadot"), "{name}");
+        }
+        for name in [
+            wasm,
+            "polkadot_runtime-v9370.compact.compressed.wasm.txt",
+            "polkadot-bridge-hub_srtool_output.json",
+            "kusama_srtool_output.json",
+        ] {
+            assert!(!is_checksum_asset(name, wasm, "polkadot"), "{name}");
         }
     }
-    Ok(None)
 }
@@ -1,110 +1,434 @@
-mod generate;
-mod github;
+mod checksum;
+pub(crate) mod generate;
+pub(crate) mod github;
 pub(crate) mod source;
-mod wasm;
+pub(crate) mod wasm;
 
+use std::collections::{BTreeMap, HashSet};
 use std::str::FromStr;
 
+use anyhow::{anyhow, bail, Result};
 use blake2_rfc::blake2b::blake2b;
-use log::{info, warn};
+use definitions::metadata::MetaValues;
+use definitions::network_specs::NetworkSpecs;
+use log::{error, info, warn};
 use sp_core::H256;
+use tokio::runtime::Runtime;
 
-use crate::config::AppConfig;
-use crate::fetch::Fetcher;
-use crate::qrs::{metadata_files, spec_files};
+use crate::common::types::{ChainPortalId, MetaVersion};
+use crate::config::{AppConfig, Chain};
+use crate::ethereum::check_account_type;
+use crate::fetch::{fetch_parallel, metadata_format, FetchedMeta, Fetcher};
+use crate::qrs::{metadata_files, spec_files, MetadataMap};
 use crate::source::{save_source_info, Source};
+use crate::updater::checksum::{verify_wasm, ChecksumMismatch};
 use crate::updater::generate::{generate_metadata_qr, generate_spec_qr};
-use crate::updater::github::fetch_latest_runtime;
-use crate::updater::wasm::{download_wasm, meta_values_from_wasm_bytes};
+use crate::updater::github::{fetch_runtimes, ReleaseSelection};
+use crate::updater::source::UpdateSource;
+use crate::updater::wasm::{
+    check_runtime_version, decompress_wasm, download_wasm, meta_values_from_wasm_bytes, WasmRuntime,
+};
 
-pub(crate) fn update_from_node(config: AppConfig, fetcher: impl Fetcher) -> anyhow::Result<()> {
+/// Fetchers of the sources available in this run
+pub(crate) struct UpdateFetchers<N, F> {
+    pub(crate) node: N,
+    /// Only available with `--dir`
+    pub(crate) files: Option<F>,
+}
+
+// Data fetched from the first source that succeeded
+enum Fetched {
+    Node {
+        // Only fetched when there is no specs QR yet
+        specs: Option<NetworkSpecs>,
+        meta: FetchedMeta,
+    },
+    // Missing versions from the selected releases
+    Wasm {
+        genesis_hash: H256,
+        runtimes: Vec<(MetaValues, Source)>,
+    },
+    // Release runtimes already have metadata QRs
+    UpToDate,
+}
+
+// Try the sources of every chain in order until one of them succeeds.
+// `source` overrides the per-chain `update_sources`.
+// Fails if any chain could not be updated
+pub(crate) fn update<N, F>(
+    config: AppConfig,
+    source: Option<UpdateSource>,
+    fetchers: UpdateFetchers<N, F>,
+    releases: ReleaseSelection,
+) -> Result<()>
+where
+    N: Fetcher + Sync,
+    F: Fetcher + Sync,
+{
     let metadata_qrs = metadata_files(&config.qr_dir)?;
     let specs_qrs = spec_files(&config.qr_dir)?;
+    let runtime = Runtime::new()?;
 
-    let mut is_changed = false;
-    for chain in config.chains {
-        if !specs_qrs.contains_key(&chain.portal_id()) {
-            let specs = fetcher.fetch_specs(&chain)?;
-            generate_spec_qr(&specs, &config.qr_dir, &chain.portal_id())?;
-            is_changed = true;
-        }
+    let chains: Vec<&Chain> = config.selected_chains().collect();
+    let fetched = fetch_parallel(&chains, config.jobs, |chain| {
+        let sources = match source {
+            Some(source) => vec![source],
+            // Explicit blocks are only supported by nodes
+            None if config.at.is_some() => vec![UpdateSource::Node],
+            None if chain.update_sources.is_empty() => vec![UpdateSource::Node],
+            None => chain.update_sources.clone(),
+        };
+        let has_specs = specs_qrs.contains_key(&chain.portal_id());
+        fetch_from_sources(chain, &sources, |source| match source {
+            UpdateSource::Node => fetch_from_node(chain, &fetchers.node, has_specs).map(Some),
+            UpdateSource::Files => match &fetchers.files {
+                Some(files) => fetch_from_node(chain, files, has_specs).map(Some),
+                None => {
+                    info!("↪️ No --dir given, skipping");
+                    Ok(None)
+                }
+            },
+            UpdateSource::Github if chain.github_release.is_none() => {
+                info!("↪️ No GitHub releases configured, skipping");
+                Ok(None)
+            }
+            UpdateSource::Github => {
+                fetch_from_github(&runtime, chain, &metadata_qrs, &releases).map(Some)
+            }
+        })
+    });
+    fetchers.node.report_failures();
 
+    let mut is_changed = false;
+    // Source of every chain, None if no source is configured for it
+    let mut summary: Vec<(ChainPortalId, Result<Option<UpdateSource>>)> = vec![];
+    for (chain, fetched) in chains.into_iter().zip(fetched) {
         info!("🔍 Checking for updates for {}", chain.name);
-        let fetched_meta = match fetcher.fetch_metadata(&chain) {
-            Ok(meta) => meta,
-            Err(e) => {
-                warn!("🤨 Failed to fetch metadata: {:?}", e);
-                continue;
+        let result = fetched.and_then(|fetched| match fetched {
+            Some((source, fetched)) => {
+                is_changed |= generate_qrs(&config, chain, fetched, &metadata_qrs)?;
+                Ok(Some(source))
             }
-        };
-        let version = fetched_meta.meta_values.version;
+            None => Ok(None),
+        });
+        if let Err(e) = &result {
+            error!("❌ Failed to update {}: {:#}", chain.portal_id(), e);
+        }
+        summary.push((chain.portal_id(), result));
+    }
 
-        // Skip if already have QR for the same version
-        if let Some(map) = metadata_qrs.get(&chain.portal_id()) {
-            if map.contains_key(&version) {
-                continue;
+    info!("📋 Update summary:");
+    let mut failed = vec![];
+    for (portal_id, result) in &summary {
+        match result {
+            Ok(Some(source)) => info!("  {}: {}", portal_id, source),
+            Ok(None) => info!("  {}: skipped", portal_id),
+            Err(e) => {
+                warn!("  {}: {:#}", portal_id, e);
+                failed.push(portal_id.as_str());
             }
         }
-        let path = generate_metadata_qr(
-            &fetched_meta.meta_values,
-            &fetched_meta.genesis_hash,
-            &config.qr_dir,
-            &chain.portal_id(),
-        )?;
-        let source = Source::Rpc {
-            block: fetched_meta.block_hash,
-        };
-        save_source_info(&path, &source)?;
-        is_changed = true;
     }
-
     if !is_changed {
         info!("🎉 Everything is up to date!");
     }
+    if !failed.is_empty() {
+        bail!("update failed for {}", failed.join(", "));
+    }
     Ok(())
 }
 
-#[tokio::main]
-pub(crate) async fn update_from_github(config: AppConfig) -> anyhow::Result<()> {
-    let metadata_qrs = metadata_files(&config.qr_dir)?;
-    for chain in config.chains {
-        info!("🔍 Checking for updates for {}", chain.name);
-        if chain.github_release.is_none() {
-            info!("↪️ No GitHub releases configured, skipping",);
-            continue;
+// Try `sources` in order until one of them returns data. `fetch` returns None
+// for sources that are not configured for the chain, which are skipped.
+// A checksum mismatch fails the chain without trying the remaining sources
+fn fetch_from_sources<F>(
+    chain: &Chain,
+    sources: &[UpdateSource],
+    fetch: F,
+) -> Result<Option<(UpdateSource, Fetched)>>
+where
+    F: Fn(UpdateSource) -> Result<Option<Fetched>>,
+{
+    let mut failed = false;
+    for &source in sources {
+        match fetch(source) {
+            Ok(Some(fetched)) => return Ok(Some((source, fetched))),
+            Ok(None) => {}
+            // A runtime that differs from its release must fail the run, not be replaced
+            Err(e) if e.is::<ChecksumMismatch>() => return Err(e),
+            Err(e) => {
+                warn!(
+                    "🤨 Failed to fetch {} from {}: {:#}",
+                    chain.portal_id(),
+                    source,
+                    e
+                );
+                failed = true;
+            }
         }
+    }
+    match failed {
+        true => bail!("all sources failed"),
+        false => Ok(None),
+    }
+}
 
-        let github_repo = chain.github_release.as_ref().unwrap();
-        let wasm = fetch_latest_runtime(github_repo, &chain.name).await?;
-        if wasm.is_none() {
-            warn!("🤨 No releases found");
-            continue;
+// Returns true if any QR was generated
+fn generate_qrs(
+    config: &AppConfig,
+    chain: &Chain,
+    fetched: Fetched,
+    metadata_qrs: &MetadataMap,
+) -> Result<bool> {
+    match fetched {
+        Fetched::Node { specs, meta } => {
+            let mut is_changed = false;
+            if let Some(specs) = specs {
+                generate_spec_qr(&specs, &config.qr_dir, &chain.portal_id())?;
+                is_changed = true;
+            }
+            is_changed |= update_metadata_from_node(config, chain, meta, metadata_qrs)?;
+            Ok(is_changed)
         }
-        let wasm = wasm.unwrap();
-        info!("📅 Found version {}", wasm.version);
-        let genesis_hash = H256::from_str(&github_repo.genesis_hash).unwrap();
-
-        // Skip if already have QR for the same version
-        if let Some(map) = metadata_qrs.get(&chain.portal_id()) {
-            if map.contains_key(&wasm.version) || map.keys().min().unwrap_or(&0) > &wasm.version {
-                info!("🎉 {} is up to date!", chain.name);
-                continue;
+        Fetched::Wasm {
+            genesis_hash,
+            runtimes,
+        } => {
+            for (meta_values, source) in runtimes {
+                let path = generate_metadata_qr(
+                    &meta_values,
+                    &genesis_hash,
+                    &config.qr_dir,
+                    &chain.portal_id(),
+                )?;
+                save_source_info(&path, &source)?;
             }
+            Ok(true)
+        }
+        Fetched::UpToDate => {
+            info!("🎉 {} is up to date!", chain.name);
+            Ok(false)
         }
-        let wasm_bytes = download_wasm(wasm.to_owned()).await?;
-        let meta_hash = blake2b(32, &[], &wasm_bytes).as_bytes().to_vec();
-        let meta_values = meta_values_from_wasm_bytes(&wasm_bytes)?;
-        let path = generate_metadata_qr(
-            &meta_values,
-            &genesis_hash,
-            &config.qr_dir,
-            &chain.portal_id(),
-        )?;
+    }
+}
+
+fn fetch_from_node(chain: &Chain, fetcher: &impl Fetcher, has_specs: bool) -> Result<Fetched> {
+    let specs = match has_specs {
+        true => None,
+        false => Some(fetcher.fetch_specs(chain)?),
+    };
+    let meta = fetcher.fetch_metadata(chain)?;
+    Ok(Fetched::Node { specs, meta })
+}
+
+// Returns true if a new metadata QR was generated
+fn update_metadata_from_node(
+    config: &AppConfig,
+    chain: &Chain,
+    fetched_meta: FetchedMeta,
+    metadata_qrs: &MetadataMap,
+) -> Result<bool> {
+    check_account_type(chain, &fetched_meta.meta_values);
+    let version = fetched_meta.meta_values.version;
+
+    // Skip if already have QR for the same version
+    if let Some(map) = metadata_qrs.get(&chain.portal_id()) {
+        if map.contains_key(&version) {
+            return Ok(false);
+        }
+    }
+    let path = generate_metadata_qr(
+        &fetched_meta.meta_values,
+        &fetched_meta.genesis_hash,
+        &config.qr_dir,
+        &chain.portal_id(),
+    )?;
+    let source = Source::Rpc {
+        block: fetched_meta.block_hash,
+        number: fetched_meta.block_number,
+        metadata_format: metadata_format(&fetched_meta.meta_values.meta),
+    };
+    save_source_info(&path, &source)?;
+    Ok(true)
+}
+
+fn fetch_from_github(
+    runtime: &Runtime,
+    chain: &Chain,
+    metadata_qrs: &MetadataMap,
+    releases: &ReleaseSelection,
+) -> Result<Fetched> {
+    let github_repo = chain
+        .github_release
+        .as_ref()
+        .ok_or_else(|| anyhow!("no GitHub releases configured"))?;
+    let wasms = runtime.block_on(fetch_runtimes(github_repo, &chain.name, releases))?;
+    if wasms.is_empty() {
+        bail!("no releases found");
+    }
+    let genesis_hash = H256::from_str(github_repo.genesis_hash.trim_start_matches("0x"))?;
+
+    for wasm in &wasms {
+        info!("📅 Found {} version {}", chain.name, wasm.version);
+    }
+    let mut runtimes = vec![];
+    for wasm in select_runtimes(wasms, metadata_qrs.get(&chain.portal_id()), releases) {
+        let wasm_bytes = runtime.block_on(download_wasm(&wasm))?;
+        // Never generate QRs from runtimes that differ from the published checksums
+        let verified = runtime.block_on(verify_wasm(&wasm, &wasm_bytes))?;
+        let code = decompress_wasm(&wasm_bytes)?;
+        let meta_values = meta_values_from_wasm_bytes(&code)?;
+        check_runtime_version(&meta_values, &chain.name, &wasm)?;
         let source = Source::Wasm {
             github_repo: format!("{}/{}", github_repo.owner, github_repo.repo),
-            hash: format!("0x{}", hex::encode(meta_hash)),
+            hash: blake2_hex(&wasm_bytes),
+            code_hash: Some(blake2_hex(&code)),
+            checksum: verified.as_ref().map(|v| v.digest.clone()),
+            srtool: verified.and_then(|v| v.srtool),
+            metadata_format: metadata_format(&meta_values.meta),
         };
-        save_source_info(&path, &source)?;
+        runtimes.push((meta_values, source));
+    }
+    match runtimes.is_empty() {
+        true => Ok(Fetched::UpToDate),
+        false => Ok(Fetched::Wasm {
+            genesis_hash,
+            runtimes,
+        }),
+    }
+}
+
+// Runtimes of the selected releases that need a metadata QR, each version once.
+// Versions that already have a QR are skipped and so are, unless the release
+// is pinned by tag, versions older than the oldest QR we keep
+fn select_runtimes<V>(
+    wasms: Vec<WasmRuntime>,
+    existing: Option<&BTreeMap<MetaVersion, V>>,
+    releases: &ReleaseSelection,
+) -> Vec<WasmRuntime> {
+    let oldest = existing.and_then(|map| map.keys().next().copied());
+    let is_pinned = matches!(releases, ReleaseSelection::Tag(_));
+    let mut versions = HashSet::new();
+    wasms
+        .into_iter()
+        .filter(|wasm| {
+            !existing.is_some_and(|map| map.contains_key(&wasm.version))
+                && (is_pinned || !oldest.is_some_and(|oldest| oldest > wasm.version))
+                && versions.insert(wasm.version)
+        })
+        .collect()
+}
+
+fn blake2_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(blake2b(32, &[], bytes).as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    // Records the sources tried and returns the given result for each of them
+    fn try_sources(
+        sources: &[UpdateSource],
+        result: impl Fn(UpdateSource) -> Result<Option<Fetched>>,
+    ) -> (Result<Option<UpdateSource>>, Vec<UpdateSource>) {
+        let tried = RefCell::new(vec![]);
+        let fetched = fetch_from_sources(&Chain::default(), sources, |source| {
+            tried.borrow_mut().push(source);
+            result(source)
+        });
+        (
+            fetched.map(|f| f.map(|(source, _)| source)),
+            tried.into_inner(),
+        )
+    }
+
+    const GITHUB_THEN_NODE: [UpdateSource; 2] = [UpdateSource::Github, UpdateSource::Node];
+
+    #[test]
+    fn fall_back_to_next_source() {
+        let (result, tried) = try_sources(&GITHUB_THEN_NODE, |source| match source {
+            UpdateSource::Github => Err(anyhow!("no releases found")),
+            _ => Ok(Some(Fetched::UpToDate)),
+        });
+        assert_eq!(result.unwrap(), Some(UpdateSource::Node));
+        assert_eq!(tried, GITHUB_THEN_NODE);
+
+        let (result, tried) = try_sources(&GITHUB_THEN_NODE, |_| Ok(Some(Fetched::UpToDate)));
+        assert_eq!(result.unwrap(), Some(UpdateSource::Github));
+        assert_eq!(tried, [UpdateSource::Github]);
+    }
+
+    #[test]
+    fn skip_unconfigured_sources() {
+        let (result, _) = try_sources(&[UpdateSource::Github], |_| Ok(None));
+        assert_eq!(result.unwrap(), None);
+
+        let (result, _) = try_sources(&GITHUB_THEN_NODE, |source| match source {
+            UpdateSource::Github => Ok(None),
+            _ => Err(anyhow!("all endpoints failed")),
+        });
+        assert!(result.is_err());
+    }
+
+    fn wasms(versions: &[u32]) -> Vec<WasmRuntime> {
+        versions
+            .iter()
+            .map(|&version| WasmRuntime {
+                chain: "polkadot".to_string(),
+                version,
+                download_url: format!("https://github.com/polkadot_runtime-v{version}.wasm")
+                    .parse()
+                    .unwrap(),
+                checksums: vec![],
+            })
+            .collect()
+    }
+
+    fn selected(
+        versions: &[u32],
+        existing: &[u32],
+        releases: ReleaseSelection,
+    ) -> Vec<MetaVersion> {
+        let existing: BTreeMap<MetaVersion, ()> = existing.iter().map(|&v| (v, ())).collect();
+        let existing = (!existing.is_empty()).then_some(&existing);
+        select_runtimes(wasms(versions), existing, &releases)
+            .into_iter()
+            .map(|wasm| wasm.version)
+            .collect()
+    }
+
+    #[test]
+    fn skip_existing_and_old_versions() {
+        let last = ReleaseSelection::Last(5);
+        assert_eq!(selected(&[9380, 9370], &[], last.clone()), [9380, 9370]);
+        assert_eq!(selected(&[9380, 9370], &[9370], last.clone()), [9380]);
+        assert_eq!(selected(&[9380, 9360], &[9370], last), [9380]);
+        assert!(selected(&[9360], &[9370, 9380], ReleaseSelection::Latest).is_empty());
+    }
+
+    #[test]
+    fn pinned_tag_can_be_older() {
+        let tag = ReleaseSelection::Tag("v0.9.36".to_string());
+        assert_eq!(selected(&[9360], &[9370, 9380], tag.clone()), [9360]);
+        assert!(selected(&[9370], &[9370, 9380], tag).is_empty());
+    }
+
+    #[test]
+    fn dedup_versions_across_releases() {
+        let last = ReleaseSelection::Last(3);
+        assert_eq!(selected(&[9380, 9380, 9370], &[], last), [9380, 9370]);
+    }
+
+    #[test]
+    fn checksum_mismatch_stops_fallback() {
+        let (result, tried) = try_sources(&GITHUB_THEN_NODE, |source| match source {
+            UpdateSource::Github => Err(anyhow!(ChecksumMismatch("blake2_256".to_string()))),
+            _ => Ok(Some(Fetched::UpToDate)),
+        });
+        assert!(result.unwrap_err().is::<ChecksumMismatch>());
+        assert_eq!(tried, [UpdateSource::Github]);
     }
-    Ok(())
 }
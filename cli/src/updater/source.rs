@@ -1,12 +1,17 @@
+use std::fmt;
 use std::str::FromStr;
 
 use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
 
-/// The source of the metadata. It can come from the running rcp node or from Github releases.
-#[derive(Debug, Clone, PartialEq)]
+/// The source of the metadata. It can come from the running rcp node, from Github releases
+/// or from local files.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub(crate) enum UpdateSource {
     Node,
     Github,
+    Files,
 }
 
 impl FromStr for UpdateSource {
@@ -16,7 +21,19 @@ impl FromStr for UpdateSource {
         match s {
             "node" => Ok(Self::Node),
             "github" => Ok(Self::Github),
+            "files" => Ok(Self::Files),
             _ => Err(anyhow!("Invalid source: {}", s)),
         }
     }
 }
+
+impl fmt::Display for UpdateSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            UpdateSource::Node => "node",
+            UpdateSource::Github => "github",
+            UpdateSource::Files => "files",
+        };
+        write!(f, "{name}")
+    }
+}
@@ -1,47 +1,65 @@
-use std::path::Path;
+use std::borrow::Cow;
+use std::io::Write;
 
-use anyhow::{anyhow, ensure};
+use anyhow::{anyhow, ensure, Context};
 use definitions::metadata::MetaValues;
 use log::info;
 use octocrab::models::repos::Asset;
+use regex::Regex;
 use reqwest::Url;
+use sp_maybe_compressed_blob::CODE_BLOB_BOMB_LIMIT;
+use tempfile::NamedTempFile;
+
+// `<chain>_runtime-v<version>.*.wasm`, e.g. `polkadot_runtime-v9370.compact.compressed.wasm`
+pub(crate) const DEFAULT_ASSET_PATTERN: &str =
+    r"^(?P<chain>[^.]+)_runtime-v(?P<version>\d+)(\.[^.]+)*\.wasm$";
 
 #[derive(Debug, PartialEq, Clone, Hash, Eq)]
 pub(crate) struct WasmRuntime {
     pub(crate) chain: String,
     pub(crate) version: u32,
     pub(crate) download_url: Url,
+    /// Checksum and srtool assets published for this runtime in the same release
+    pub(crate) checksums: Vec<ChecksumAsset>,
 }
 
-impl TryFrom<Asset> for WasmRuntime {
-    type Error = anyhow::Error;
-
-    fn try_from(asset: Asset) -> Result<Self, Self::Error> {
-        if !asset.name.ends_with(".wasm") {
-            return Err(anyhow!("{} has no .wasm extension", asset.name));
-        }
-        let runtime_info = asset
-            .name
-            .split('.')
-            .next()
-            .ok_or_else(|| anyhow!("no runtime info found"))?;
-        let mut split = runtime_info.split("_runtime-v");
-        let chain = split.next().ok_or_else(|| anyhow!("no chain name found"))?;
-        let version: u32 = split
-            .next()
-            .ok_or_else(|| anyhow!("no metadata version found"))?
-            .parse()
-            .unwrap();
+#[derive(Debug, PartialEq, Clone, Hash, Eq)]
+pub(crate) struct ChecksumAsset {
+    pub(crate) name: String,
+    pub(crate) download_url: Url,
+}
 
+impl WasmRuntime {
+    pub(crate) fn from_asset(asset: &Asset, pattern: &Regex) -> anyhow::Result<Self> {
+        let (chain, version) = parse_asset_name(&asset.name, pattern)?;
         Ok(Self {
-            chain: String::from(chain),
+            chain,
             version,
-            download_url: asset.browser_download_url,
+            download_url: asset.browser_download_url.clone(),
+            checksums: vec![],
         })
     }
 }
 
-pub(crate) async fn download_wasm(wasm: WasmRuntime) -> anyhow::Result<Vec<u8>> {
+// Chain name and spec version from the `chain` and `version` groups of the pattern
+fn parse_asset_name(name: &str, pattern: &Regex) -> anyhow::Result<(String, u32)> {
+    let captures = pattern
+        .captures(name)
+        .ok_or_else(|| anyhow!("{} does not match {}", name, pattern))?;
+    let chain = captures
+        .name("chain")
+        .ok_or_else(|| anyhow!("no chain name found in {}", name))?;
+    let version = captures
+        .name("version")
+        .ok_or_else(|| anyhow!("no runtime version found in {}", name))?;
+    let version = version
+        .as_str()
+        .parse()
+        .context(format!("invalid runtime version in {name}"))?;
+    Ok((chain.as_str().to_string(), version))
+}
+
+pub(crate) async fn download_wasm(wasm: &WasmRuntime) -> anyhow::Result<Vec<u8>> {
     info!("⬇️  Downloading {} runtime...", &wasm.chain);
     let response = reqwest::get(wasm.download_url.clone()).await?;
     ensure!(
@@ -53,10 +71,130 @@ pub(crate) async fn download_wasm(wasm: WasmRuntime) -> anyhow::Result<Vec<u8>>
     Ok(response.bytes().await?.to_vec())
 }
 
-pub(crate) fn meta_values_from_wasm_bytes(wasm_bytes: &Vec<u8>) -> anyhow::Result<MetaValues> {
-    let filename = "/tmp/wasm";
-    std::fs::write(Path::new(&filename), wasm_bytes)?;
-    let meta = MetaValues::from_wasm_file(filename)
+// Runtime code of `compact.compressed` assets is zstd compressed with a magic prefix.
+// Other assets are returned as they are
+pub(crate) fn decompress_wasm(wasm_bytes: &[u8]) -> anyhow::Result<Cow<[u8]>> {
+    let code = sp_maybe_compressed_blob::decompress(wasm_bytes, CODE_BLOB_BOMB_LIMIT)
+        .map_err(|e| anyhow!("failed to decompress runtime: {}", e))?;
+    if let Cow::Owned(code) = &code {
+        info!(
+            "🗜  Decompressed runtime from {} to {} bytes",
+            wasm_bytes.len(),
+            code.len()
+        );
+    }
+    Ok(code)
+}
+
+// `definitions` only reads runtimes from files. Every call gets its own private temp file,
+// so that concurrent runs never read each other's runtime
+pub(crate) fn meta_values_from_wasm_bytes(wasm_bytes: &[u8]) -> anyhow::Result<MetaValues> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(wasm_bytes)?;
+    file.flush()?;
+    let path = file
+        .path()
+        .to_str()
+        .ok_or_else(|| anyhow!("non UTF-8 temp file path"))?;
+    let meta = MetaValues::from_wasm_file(path)
         .map_err(|_e| anyhow!("error converting wasm to metadata"))?;
     Ok(meta)
 }
+
+// Runtime version embedded in the wasm must match the chain and the asset name
+pub(crate) fn check_runtime_version(
+    meta_values: &MetaValues,
+    chain_name: &str,
+    wasm: &WasmRuntime,
+) -> anyhow::Result<()> {
+    ensure!(
+        meta_values.name.to_lowercase() == chain_name,
+        "runtime of {} has spec name {}, expected {}",
+        wasm.chain,
+        meta_values.name,
+        chain_name
+    );
+    ensure!(
+        meta_values.version == wasm.version,
+        "runtime of {} has spec version {}, but its asset name says {}",
+        wasm.chain,
+        meta_values.version,
+        wasm.version
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_default_asset_names() {
+        let pattern = Regex::new(DEFAULT_ASSET_PATTERN).unwrap();
+        assert_eq!(
+            parse_asset_name("polkadot_runtime-v9370.compact.compressed.wasm", &pattern).unwrap(),
+            ("polkadot".to_string(), 9370)
+        );
+        assert_eq!(
+            parse_asset_name(
+                "asset-hub-polkadot_runtime-v1003000.compact.compressed.wasm",
+                &pattern
+            )
+            .unwrap(),
+            ("asset-hub-polkadot".to_string(), 1003000)
+        );
+        assert!(parse_asset_name("polkadot_runtime-v9370.json", &pattern).is_err());
+        assert!(parse_asset_name("polkadot_runtime-v99999999999.wasm", &pattern).is_err());
+    }
+
+    #[test]
+    fn parse_custom_asset_names() {
+        let pattern = Regex::new(r"^(?P<chain>\w+)-(?P<version>\d+)\.wasm$").unwrap();
+        assert_eq!(
+            parse_asset_name("moonbeam-2801.wasm", &pattern).unwrap(),
+            ("moonbeam".to_string(), 2801)
+        );
+        let pattern = Regex::new(r"^(?P<chain>\w+)\.wasm$").unwrap();
+        assert!(parse_asset_name("moonbeam.wasm", &pattern).is_err());
+    }
+
+    #[test]
+    fn decompress_compact_compressed_wasm() {
+        let code = b"\0asm runtime code".repeat(100);
+        assert_eq!(decompress_wasm(&code).unwrap(), Cow::Borrowed(&code[..]));
+
+        let compressed = sp_maybe_compressed_blob::compress(&code, CODE_BLOB_BOMB_LIMIT).unwrap();
+        assert_ne!(compressed, code);
+        assert_eq!(decompress_wasm(&compressed).unwrap().as_ref(), &code[..]);
+    }
+
+    #[test]
+    fn runtime_version_matches_asset() {
+        let wasm = WasmRuntime {
+            chain: "asset-hub-polkadot".to_string(),
+            version: 1003000,
+            download_url: Url::parse("https://example.com/runtime.wasm").unwrap(),
+            checksums: vec![],
+        };
+        let meta_values = |name: &str, version| MetaValues {
+            name: name.to_string(),
+            version,
+            optional_base58prefix: None,
+            warn_incomplete_extensions: false,
+            meta: vec![],
+        };
+        assert!(
+            check_runtime_version(&meta_values("statemint", 1003000), "statemint", &wasm).is_ok()
+        );
+        // Spec names are compared like in `check_chain`
+        assert!(
+            check_runtime_version(&meta_values("Statemint", 1003000), "statemint", &wasm).is_ok()
+        );
+        assert!(
+            check_runtime_version(&meta_values("statemine", 1003000), "statemint", &wasm).is_err()
+        );
+        assert!(
+            check_runtime_version(&meta_values("statemint", 1002000), "statemint", &wasm).is_err()
+        );
+    }
+}
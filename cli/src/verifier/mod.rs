@@ -1,13 +1,21 @@
 mod qr;
 
+use std::collections::HashSet;
+
 use anyhow::Result;
 use log::info;
 
+use crate::qrs::qrs_in_dir;
 use crate::verifier::qr::validate_signed_qrs;
 use crate::AppConfig;
 
 pub(crate) fn verify(config: AppConfig) -> Result<()> {
-    validate_signed_qrs(&config.qr_dir, &config.verifier.public_key)?;
+    let mut qrs = qrs_in_dir(&config.qr_dir)?;
+    if !config.filter.is_empty() {
+        let selected: HashSet<String> = config.selected_chains().map(|c| c.portal_id()).collect();
+        qrs.retain(|qr| selected.contains(&qr.file_name.chain));
+    }
+    validate_signed_qrs(&qrs, &config.verifier.public_key)?;
     info!("✅ Done");
     Ok(())
 }
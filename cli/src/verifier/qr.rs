@@ -1,5 +1,3 @@
-use std::path::Path;
-
 use anyhow::{anyhow, bail, ensure, Result};
 use definitions::error::TransferContent;
 use definitions::helpers::multisigner_to_public;
@@ -11,16 +9,14 @@ use transaction_parsing::check_signature::pass_crypto;
 
 use crate::common::camera::read_qr_file;
 use crate::common::path::{ContentType, QrFileName, QrPath};
-use crate::qrs::qrs_in_dir;
 
-pub(crate) fn validate_signed_qrs(dir: impl AsRef<Path>, public_key: &str) -> Result<()> {
-    let all_qrs = qrs_in_dir(&dir)?;
+pub(crate) fn validate_signed_qrs(all_qrs: &[QrPath], public_key: &str) -> Result<()> {
     // Quick check that latest files are signed
-    for qr in &all_qrs {
+    for qr in all_qrs {
         ensure!(qr.file_name.is_signed, "{} is not signed", qr.file_name);
     }
 
-    for qr in &all_qrs {
+    for qr in all_qrs {
         if let ContentType::Metadata(_) = qr.file_name.content_type {
             let f_name = &qr.file_name;
             match validate_metadata_qr(qr, public_key) {
@@ -12,4 +12,18 @@ mod cli_tests {
             assert.success().code(0);
         }
     }
+
+    #[cfg(test)]
+    mod validate_config {
+        use assert_cmd::Command;
+
+        #[test]
+        fn repo_config_is_valid() {
+            let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+            let assert = cmd
+                .args(["--config", "../config.toml", "validate-config"])
+                .assert();
+            assert.success().code(0);
+        }
+    }
 }
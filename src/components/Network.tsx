@@ -198,6 +198,24 @@ export const Network = ({
                             slicer={hashSlicer}
                           />
                         </Row>
+                        {(metadataQr?.file.source as WasmSource).code_hash !==
+                          undefined && (
+                          <Row title="Code hash">
+                            <Copyable
+                              value={
+                                (metadataQr?.file.source as WasmSource)
+                                  .code_hash as string
+                              }
+                              slicer={hashSlicer}
+                            />
+                          </Row>
+                        )}
+                        {(metadataQr?.file.source as WasmSource).checksum !==
+                          undefined && (
+                          <Row title="Verified checksum">
+                            {(metadataQr?.file.source as WasmSource).checksum}
+                          </Row>
+                        )}
                       </ul>
                     )}
                     {metadataQr?.file.source?.type === "Rpc" && (
@@ -208,9 +226,20 @@ export const Network = ({
                             slicer={hashSlicer}
                           />
                         </Row>
+                        {(metadataQr?.file.source as RpcSource).number !==
+                          undefined && (
+                          <Row title="Source block number">
+                            #{(metadataQr?.file.source as RpcSource).number}
+                          </Row>
+                        )}
                       </ul>
                     )}
                     <Row title="Metadata Version">#{metadataQr?.version}</Row>
+                    {metadataQr?.file.source?.metadata_format !== undefined && (
+                      <Row title="Metadata Format">
+                        V{metadataQr?.file.source?.metadata_format}
+                      </Row>
+                    )}
                     <Row title="Signed by">{metadataQr?.file.signedBy}</Row>
                   </div>
                 )}
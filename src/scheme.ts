@@ -6,8 +6,10 @@ export interface ChainSpec {
   unit: string;
   base58prefix: number;
   decimals: number;
+  encryption: string;
   logo: string;
   liveMetaVersion: number;
+  liveMetadataFormat?: number;
   metadataQr?: MetadataQr;
   latestMetadata: string;
   specsQr: QrInfo;
@@ -35,10 +37,15 @@ interface SourceBase {
 export interface WasmSource extends SourceBase {
   github_repo: string;
   hash: string;
+  code_hash?: string;
+  checksum?: string;
+  metadata_format?: number;
 }
 
 export interface RpcSource extends SourceBase {
   block: string;
+  number?: number;
+  metadata_format?: number;
 }
 
 export interface AddToSignerInterface {